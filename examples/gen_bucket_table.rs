@@ -0,0 +1,44 @@
+//! Clusters every canonical flop or turn hand into buckets by equity histogram
+//!
+//! Usage: `cargo run --release --features indexer --example gen_bucket_table -- <flop|turn> <file> [buckets] [runouts] [threads]`
+
+use std::env;
+use std::time::Instant;
+
+use rust_poker::card_abstraction::{AbstractionOptions, BucketTable};
+use rust_poker::HandIndexer;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!(
+            "usage: {} <flop|turn> <file> [buckets] [runouts] [threads]",
+            args[0]
+        );
+        std::process::exit(1);
+    }
+    let (indexer, round) = match args[1].as_str() {
+        "flop" => (HandIndexer::init(2, [2, 3].to_vec()), 1),
+        "turn" => (HandIndexer::init(3, [2, 3, 1].to_vec()), 2),
+        _ => {
+            eprintln!("round must be flop or turn");
+            std::process::exit(1);
+        }
+    };
+    let options = AbstractionOptions {
+        n_buckets: args.get(3).and_then(|n| n.parse().ok()).unwrap_or(200),
+        n_runouts: args.get(4).and_then(|n| n.parse().ok()),
+        n_threads: args.get(5).and_then(|n| n.parse().ok()).unwrap_or(8),
+        ..AbstractionOptions::default()
+    };
+
+    let start = Instant::now();
+    let table = BucketTable::build(&indexer, round, &options).expect("could not build buckets");
+    println!(
+        "clustered {} hands into {} buckets in {:.1}s",
+        table.buckets.len(),
+        table.n_buckets,
+        start.elapsed().as_secs_f64()
+    );
+    table.save(&args[2]).expect("could not write bucket table");
+}
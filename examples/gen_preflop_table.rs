@@ -0,0 +1,29 @@
+//! Generates the exact heads-up preflop equity table
+//!
+//! Usage: `cargo run --release --example gen_preflop_table -- <file> [threads]`
+//!
+//! Load the file with `PreflopTable::load` and pass it in `EquityOptions::preflop_table`
+//! to answer heads-up preflop queries from it
+
+use std::env;
+use std::time::Instant;
+
+use rust_poker::equity_calculator::PreflopTable;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("usage: {} <file> [threads]", args[0]);
+        std::process::exit(1);
+    }
+    let n_threads = args.get(2).and_then(|n| n.parse().ok()).unwrap_or(8);
+
+    let start = Instant::now();
+    let table = PreflopTable::generate(n_threads);
+    println!(
+        "enumerated {} matchups in {:.1}s",
+        table.len(),
+        start.elapsed().as_secs_f64()
+    );
+    table.save(&args[1]).expect("could not write preflop table");
+}
@@ -0,0 +1,666 @@
+use std::fs::File;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+#[cfg(feature = "indexer")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+use read_write::VecIO;
+use thiserror::Error;
+
+use crate::constants::CARD_COUNT;
+#[cfg(feature = "indexer")]
+use crate::equity_calculator::batch_seed;
+use crate::hand_evaluator::{evaluate, Hand, CARDS};
+
+#[cfg(feature = "indexer")]
+use crate::HandIndexer;
+
+/// First word of a bucket table file
+const TABLE_MAGIC: u32 = 0x4255_4B54;
+/// Version of the bucket table file layout
+const TABLE_VERSION: u32 = 1;
+/// Number of cards on a river board
+const BOARD_CARDS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum AbstractionError {
+    #[error("board must be a flop or turn")]
+    NotFlopOrTurn,
+    #[error("need at least one bucket and one histogram bin")]
+    InvalidOptions,
+    #[error("fewer hands than buckets")]
+    TooManyBuckets,
+    #[error("table round does not match the indexer")]
+    RoundMismatch,
+    #[error("expected {0} distinct cards")]
+    InvalidCards(usize),
+    #[error("need two hole cards and up to five distinct board cards")]
+    InvalidHand,
+}
+
+/// Options for building a card abstraction
+#[derive(Debug, Clone)]
+pub struct AbstractionOptions {
+    /// Number of buckets to cluster the hands into
+    pub n_buckets: usize,
+    /// Number of equal width equity bins in each histogram
+    pub n_bins: usize,
+    /// Runouts sampled for each histogram, every runout is used if `None`
+    pub n_runouts: Option<usize>,
+    /// Max number of k-means iterations
+    pub max_iterations: usize,
+    /// Number of threads to use
+    pub n_threads: usize,
+    /// Seed for runout sampling and k-means initialization
+    ///
+    /// A random seed is used if `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for AbstractionOptions {
+    fn default() -> Self {
+        AbstractionOptions {
+            n_buckets: 200,
+            n_bins: 50,
+            n_runouts: None,
+            max_iterations: 100,
+            n_threads: 4,
+            seed: None,
+        }
+    }
+}
+
+/// Equity of a hand on a river board against a uniformly random hand
+fn river_equity(hole_mask: u64, board_mask: u64) -> f64 {
+    let board = Hand::from_bit_mask(board_mask);
+    let hero =
+        CARDS[hole_mask.trailing_zeros() as usize] + CARDS[63 - hole_mask.leading_zeros() as usize];
+    let hero_score = evaluate(&(board + hero));
+    let used_mask = hole_mask | board_mask;
+    let deck: Vec<u8> = (0..CARD_COUNT)
+        .filter(|c| (used_mask & (1u64 << c)) == 0)
+        .collect();
+    let mut score = 0u32;
+    let mut count = 0u32;
+    for (i, c1) in deck.iter().enumerate() {
+        for c2 in deck[i + 1..].iter() {
+            let villain_score = evaluate(&(board + Hand::from_hole_cards(*c1, *c2)));
+            score += match hero_score.cmp(&villain_score) {
+                std::cmp::Ordering::Greater => 2,
+                std::cmp::Ordering::Equal => 1,
+                std::cmp::Ordering::Less => 0,
+            };
+            count += 1;
+        }
+    }
+    f64::from(score) / f64::from(2 * count)
+}
+
+/// Distribution of a hand's river equity against a random hand over the runouts
+///
+/// Returns the fraction of runouts in each of `n_bins` equal width equity bins.
+/// Every runout is evaluated unless `n_runouts` is given,
+/// then that many runouts are drawn with replacement.
+/// Fails with `InvalidOptions` if `n_bins` is 0, and with `InvalidHand`
+/// unless `cards` are two hole cards and up to five board cards, all distinct
+///
+/// # Arguments
+///
+/// * `cards` Two hole cards followed by a flop or turn
+/// * `n_bins` Number of histogram bins
+/// * `n_runouts` Number of runouts to sample, or `None` for all of them
+/// * `rng` Random number generator used to sample runouts
+///
+/// # Example
+/// ```
+/// use rust_poker::card_abstraction::equity_histogram;
+/// // pocket aces on a 2 7 9 J turn, cards are 4 * rank + suit
+/// let histogram =
+///     equity_histogram(&[51, 50, 0, 21, 30, 37], 10, None, &mut rand::thread_rng()).unwrap();
+/// assert!((histogram.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+/// // most rivers leave the aces with 80 to 90% against a random hand
+/// assert!(histogram[8] > 0.5);
+/// ```
+pub fn equity_histogram<R: Rng>(
+    cards: &[u8],
+    n_bins: usize,
+    n_runouts: Option<usize>,
+    rng: &mut R,
+) -> Result<Vec<f32>, AbstractionError> {
+    if n_bins == 0 {
+        return Err(AbstractionError::InvalidOptions);
+    }
+    let used_mask = cards
+        .iter()
+        .filter(|c| **c < CARD_COUNT)
+        .fold(0u64, |mask, c| mask | (1u64 << c));
+    if cards.len() < 2
+        || cards.len() > 2 + BOARD_CARDS
+        || used_mask.count_ones() as usize != cards.len()
+    {
+        return Err(AbstractionError::InvalidHand);
+    }
+    let hole_mask = (1u64 << cards[0]) | (1u64 << cards[1]);
+    let board_mask = used_mask & !hole_mask;
+    let deck: Vec<u8> = (0..CARD_COUNT)
+        .filter(|c| (used_mask & (1u64 << c)) == 0)
+        .collect();
+    let cards_left = BOARD_CARDS - (cards.len() - 2);
+
+    let mut histogram = vec![0f32; n_bins];
+    let mut add = |runout_mask: u64| {
+        let equity = river_equity(hole_mask, board_mask | runout_mask);
+        let bin = ((equity * n_bins as f64) as usize).min(n_bins - 1);
+        histogram[bin] += 1.0;
+    };
+    match n_runouts {
+        Some(n) => {
+            for _ in 0..n {
+                let runout_mask = deck
+                    .choose_multiple(rng, cards_left)
+                    .fold(0u64, |mask, c| mask | (1u64 << c));
+                add(runout_mask);
+            }
+        }
+        None => for_each_runout(&deck, cards_left, 0, 0, &mut add),
+    }
+    let total: f32 = histogram.iter().sum();
+    histogram.iter_mut().for_each(|h| *h /= total);
+    Ok(histogram)
+}
+
+/// Call `f` with the mask of every `count` card subset of `deck[start..]`
+fn for_each_runout<F: FnMut(u64)>(deck: &[u8], count: usize, start: usize, mask: u64, f: &mut F) {
+    if count == 0 {
+        f(mask);
+        return;
+    }
+    for (i, card) in deck.iter().enumerate().skip(start) {
+        for_each_runout(deck, count - 1, i + 1, mask | (1u64 << card), f);
+    }
+}
+
+/// Earth mover's distance between two histograms with the same bins
+///
+/// Both histograms should sum to the same total.
+/// Moving mass to the next bin costs one
+///
+/// # Example
+/// ```
+/// use rust_poker::card_abstraction::emd;
+/// assert_eq!(emd(&[1.0, 0.0, 0.0], &[0.0, 0.0, 1.0]), 2.0);
+/// assert_eq!(emd(&[0.5, 0.5, 0.0], &[0.0, 0.5, 0.5]), 1.0);
+/// ```
+pub fn emd(a: &[f32], b: &[f32]) -> f32 {
+    let mut carried = 0f32;
+    let mut distance = 0f32;
+    for (x, y) in a.iter().zip(b.iter()) {
+        carried += x - y;
+        distance += carried.abs();
+    }
+    distance
+}
+
+/// Index and distance of the closest centroid
+fn nearest(point: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    let mut best = (0, f32::INFINITY);
+    for (i, c) in centroids.iter().enumerate() {
+        let d = emd(point, c);
+        if d < best.1 {
+            best = (i, d);
+        }
+    }
+    best
+}
+
+/// Run `f` on every index below `n`, split between threads
+fn par_map<T: Send + Default + Clone, F: Fn(usize) -> T + Sync>(
+    n: usize,
+    n_threads: usize,
+    f: F,
+) -> Vec<T> {
+    let mut results = vec![T::default(); n];
+    let chunk_size = ((n + n_threads - 1) / n_threads.max(1)).max(1);
+    crossbeam::scope(|scope| {
+        for (chunk_idx, chunk) in results.chunks_mut(chunk_size).enumerate() {
+            let f = &f;
+            scope.spawn(move |_| {
+                for (i, r) in chunk.iter_mut().enumerate() {
+                    *r = f(chunk_idx * chunk_size + i);
+                }
+            });
+        }
+    })
+    .unwrap();
+    results
+}
+
+/// Cluster histograms into `n_buckets` buckets with k-means under earth mover's distance
+///
+/// Centroids start with k-means++ and are updated to the mean histogram of their bucket.
+/// Returns the bucket of every histogram and the centroids
+///
+/// # Arguments
+///
+/// * `histograms` Histograms to cluster, all with the same number of bins
+/// * `options` Number of buckets, iterations, threads and seed
+///
+/// # Example
+/// ```
+/// use rust_poker::card_abstraction::{kmeans_emd, AbstractionOptions};
+/// let histograms = vec![
+///     vec![1.0, 0.0, 0.0],
+///     vec![0.9, 0.1, 0.0],
+///     vec![0.0, 0.1, 0.9],
+///     vec![0.0, 0.0, 1.0],
+/// ];
+/// let options = AbstractionOptions {
+///     n_buckets: 2,
+///     seed: Some(1),
+///     ..AbstractionOptions::default()
+/// };
+/// let (buckets, _) = kmeans_emd(&histograms, &options).unwrap();
+/// assert_eq!(buckets[0], buckets[1]);
+/// assert_ne!(buckets[1], buckets[2]);
+/// ```
+pub fn kmeans_emd(
+    histograms: &[Vec<f32>],
+    options: &AbstractionOptions,
+) -> Result<(Vec<u32>, Vec<Vec<f32>>), AbstractionError> {
+    let k = options.n_buckets;
+    if k == 0 || options.n_bins == 0 {
+        return Err(AbstractionError::InvalidOptions);
+    }
+    if histograms.len() < k {
+        return Err(AbstractionError::TooManyBuckets);
+    }
+    let n_threads = options.n_threads.max(1);
+    let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    // k-means++, each new centroid drawn in proportion to squared distance
+    let mut centroids = vec![histograms[rng.gen_range(0, histograms.len())].clone()];
+    let mut distances = vec![f32::INFINITY; histograms.len()];
+    while centroids.len() < k {
+        let last = centroids.last().unwrap();
+        let updated = par_map(histograms.len(), n_threads, |i| {
+            distances[i].min(emd(&histograms[i], last))
+        });
+        distances = updated;
+        let total: f64 = distances.iter().map(|d| f64::from(d * d)).sum();
+        let next = if total > 0.0 {
+            let mut target = rng.gen::<f64>() * total;
+            distances
+                .iter()
+                .position(|d| {
+                    target -= f64::from(d * d);
+                    target < 0.0
+                })
+                .unwrap_or(histograms.len() - 1)
+        } else {
+            // every histogram is on a centroid already
+            rng.gen_range(0, histograms.len())
+        };
+        centroids.push(histograms[next].clone());
+    }
+
+    let mut buckets = vec![u32::MAX; histograms.len()];
+    for _ in 0..options.max_iterations {
+        let assigned = par_map(histograms.len(), n_threads, |i| {
+            nearest(&histograms[i], &centroids).0 as u32
+        });
+        if assigned == buckets {
+            break;
+        }
+        buckets = assigned;
+        let mut sums = vec![vec![0f64; options.n_bins]; k];
+        let mut counts = vec![0usize; k];
+        for (h, b) in histograms.iter().zip(buckets.iter()) {
+            counts[*b as usize] += 1;
+            for (s, x) in sums[*b as usize].iter_mut().zip(h.iter()) {
+                *s += f64::from(*x);
+            }
+        }
+        // an empty bucket keeps its old centroid
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count > 0 {
+                *centroid = sum.iter().map(|s| (s / count as f64) as f32).collect();
+            }
+        }
+    }
+    Ok((buckets, centroids))
+}
+
+/// Maps every hand index of a round to a bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketTable {
+    /// Indexer round the table is for, 1 for the flop and 2 for the turn
+    pub round: u32,
+    /// Number of buckets
+    pub n_buckets: u32,
+    /// Bucket of each hand index
+    pub buckets: Vec<u32>,
+}
+
+impl BucketTable {
+    /// Bucket of a hand index, `None` if the index is not in the table
+    pub fn bucket(&self, index: u64) -> Option<u32> {
+        self.buckets.get(index as usize).copied()
+    }
+
+    /// Write the table to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut data = Vec::with_capacity(5 + self.buckets.len());
+        data.extend_from_slice(&[
+            TABLE_MAGIC,
+            TABLE_VERSION,
+            self.round,
+            self.n_buckets,
+            self.buckets.len() as u32,
+        ]);
+        data.extend_from_slice(&self.buckets);
+        File::create(path)?.write_slice_to_file::<u32>(&data)
+    }
+
+    /// Read a table written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<BucketTable> {
+        let data = File::open(path)?.read_vec_from_file::<u32>()?;
+        let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg.to_string());
+        if data.len() < 5 || data[0] != TABLE_MAGIC {
+            return Err(invalid("not a bucket table file"));
+        }
+        if data[1] != TABLE_VERSION {
+            return Err(invalid("unsupported bucket table version"));
+        }
+        let n = data[4] as usize;
+        if data.len() != 5 + n {
+            return Err(invalid("bucket table file is truncated"));
+        }
+        Ok(BucketTable {
+            round: data[2],
+            n_buckets: data[3],
+            buckets: data[5..].to_vec(),
+        })
+    }
+}
+
+/// Total number of cards dealt up to a round of an indexer
+#[cfg(feature = "indexer")]
+fn cards_in_round(indexer: &HandIndexer, round: u32) -> usize {
+    indexer.cards_per_round[..=round as usize]
+        .iter()
+        .map(|c| usize::from(*c))
+        .sum()
+}
+
+/// Equity histogram of every canonical hand of a flop or turn round
+///
+/// Flop histograms use an indexer made with `HandIndexer::init(2, vec![2, 3])`,
+/// turn histograms one made with `HandIndexer::init(3, vec![2, 3, 1])`.
+/// Enumerating every runout of every flop takes hours, `n_runouts` trades accuracy for time
+///
+/// # Arguments
+///
+/// * `indexer` Indexer with two hole cards in its first round
+/// * `round` Round to compute histograms for
+/// * `options` Histogram bins, runouts, threads and seed
+#[cfg(feature = "indexer")]
+pub fn round_histograms(
+    indexer: &HandIndexer,
+    round: u32,
+    options: &AbstractionOptions,
+) -> Result<Vec<Vec<f32>>, AbstractionError> {
+    if u64::from(round) >= indexer.rounds {
+        return Err(AbstractionError::NotFlopOrTurn);
+    }
+    let n_cards = cards_in_round(indexer, round);
+    if n_cards != 5 && n_cards != 6 {
+        return Err(AbstractionError::NotFlopOrTurn);
+    }
+    if options.n_bins == 0 {
+        return Err(AbstractionError::InvalidOptions);
+    }
+    let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+    let n_hands = indexer.size(round) as usize;
+    let n_threads = options.n_threads.max(1);
+    let next = AtomicUsize::new(0);
+    let mut histograms = vec![Vec::new(); n_hands];
+    crossbeam::scope(|scope| {
+        let handles: Vec<_> = (0..n_threads)
+            .map(|_| {
+                scope.spawn(|_| {
+                    let mut done = Vec::new();
+                    let mut cards = [0u8; 7];
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::SeqCst);
+                        if idx >= n_hands {
+                            break;
+                        }
+                        indexer.get_hand(round, idx as u64, &mut cards);
+                        let mut rng = SmallRng::seed_from_u64(batch_seed(seed, idx as u64));
+                        let histogram = equity_histogram(
+                            &cards[..n_cards],
+                            options.n_bins,
+                            options.n_runouts,
+                            &mut rng,
+                        )?;
+                        done.push((idx, histogram));
+                    }
+                    Ok(done)
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (idx, histogram) in handle.join().unwrap()? {
+                histograms[idx] = histogram;
+            }
+        }
+        Ok(())
+    })
+    .unwrap()?;
+    Ok(histograms)
+}
+
+#[cfg(feature = "indexer")]
+impl BucketTable {
+    /// Cluster every canonical hand of a flop or turn round into buckets
+    ///
+    /// Computes `round_histograms` and clusters them with `kmeans_emd`
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rust_poker::HandIndexer;
+    /// use rust_poker::card_abstraction::{AbstractionOptions, BucketTable};
+    /// let indexer = HandIndexer::init(2, [2, 3].to_vec());
+    /// let options = AbstractionOptions {
+    ///     n_runouts: Some(100),
+    ///     n_threads: 8,
+    ///     ..AbstractionOptions::default()
+    /// };
+    /// let table = BucketTable::build(&indexer, 1, &options).unwrap();
+    /// table.save("flop_buckets.dat").unwrap();
+    /// ```
+    pub fn build(
+        indexer: &HandIndexer,
+        round: u32,
+        options: &AbstractionOptions,
+    ) -> Result<BucketTable, AbstractionError> {
+        let histograms = round_histograms(indexer, round, options)?;
+        let (buckets, _) = kmeans_emd(&histograms, options)?;
+        Ok(BucketTable {
+            round,
+            n_buckets: options.n_buckets as u32,
+            buckets,
+        })
+    }
+
+    /// Bucket of a hand, given as hole cards followed by the board
+    ///
+    /// The table's round must be the indexer's last round,
+    /// and `cards` must hold every card dealt up to it
+    pub fn lookup(&self, indexer: &HandIndexer, cards: &[u8]) -> Result<u32, AbstractionError> {
+        if u64::from(self.round) + 1 != indexer.rounds {
+            return Err(AbstractionError::RoundMismatch);
+        }
+        let n_cards = cards_in_round(indexer, self.round);
+        let mask = cards
+            .iter()
+            .filter(|c| **c < CARD_COUNT)
+            .fold(0u64, |mask, c| mask | (1u64 << c));
+        if cards.len() != n_cards || mask.count_ones() as usize != n_cards {
+            return Err(AbstractionError::InvalidCards(n_cards));
+        }
+        self.bucket(indexer.get_index(cards))
+            .ok_or(AbstractionError::RoundMismatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+    use test::Bencher;
+
+    fn cards(text: &str) -> Vec<u8> {
+        let mask = get_card_mask(text);
+        (0..CARD_COUNT)
+            .filter(|c| (mask & (1u64 << c)) != 0)
+            .collect()
+    }
+
+    #[test]
+    fn test_river_equity() {
+        // a royal flush can't lose
+        let equity = river_equity(get_card_mask("AhKh"), get_card_mask("QhJhTh2c3d"));
+        assert_eq!(equity, 1.0);
+        // the board plays for everyone but a ten or a pair of nines
+        let equity = river_equity(get_card_mask("2s3s"), get_card_mask("AhKdQcJs9h"));
+        assert!(equity > 0.0 && equity < 0.5);
+    }
+
+    #[test]
+    fn test_histogram_sampled() {
+        let mut hand = cards("AsAh");
+        hand.extend(cards("2c7d9h"));
+        let mut rng = SmallRng::seed_from_u64(5);
+        let exact = equity_histogram(&hand, 10, None, &mut rng).unwrap();
+        let sampled = equity_histogram(&hand, 10, Some(400), &mut rng).unwrap();
+        assert!((sampled.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(emd(&exact, &sampled) < 0.2);
+    }
+
+    #[test]
+    fn test_histogram_invalid() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut hand = cards("AsAh");
+        hand.extend(cards("2c7d9h"));
+        assert!(matches!(
+            equity_histogram(&hand, 0, None, &mut rng),
+            Err(AbstractionError::InvalidOptions)
+        ));
+        for bad in &[
+            vec![51],
+            cards("AsAhKsKhQsQhJsJh"),
+            vec![51, 50, 0, 0, 21],
+            vec![51, 50, 0, 52, 21],
+        ] {
+            assert!(matches!(
+                equity_histogram(bad, 10, Some(10), &mut rng),
+                Err(AbstractionError::InvalidHand)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_kmeans_separates_hands() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let boards = ["2c7d9h", "Kd8s3c", "QhJh4s"];
+        let mut histograms = Vec::new();
+        for board in boards.iter() {
+            for hole in ["AsAd", "6c5c"].iter() {
+                let mut hand = cards(hole);
+                hand.extend(cards(board));
+                histograms.push(equity_histogram(&hand, 20, Some(200), &mut rng).unwrap());
+            }
+        }
+        let options = AbstractionOptions {
+            n_buckets: 2,
+            n_bins: 20,
+            seed: Some(3),
+            ..AbstractionOptions::default()
+        };
+        let (buckets, centroids) = kmeans_emd(&histograms, &options).unwrap();
+        assert_eq!(centroids.len(), 2);
+        // overpairs end up together, away from the low suited hands
+        assert_eq!(buckets[0], buckets[2]);
+        assert_eq!(buckets[0], buckets[4]);
+        assert_ne!(buckets[0], buckets[1]);
+        assert!(matches!(
+            kmeans_emd(&histograms[..1], &options),
+            Err(AbstractionError::TooManyBuckets)
+        ));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let table = BucketTable {
+            round: 1,
+            n_buckets: 3,
+            buckets: vec![0, 2, 1, 1, 0],
+        };
+        let path =
+            std::env::temp_dir().join(format!("rust_poker_buckets_{}.dat", std::process::id()));
+        assert_eq!(table.bucket(1), Some(2));
+        assert_eq!(table.bucket(5), None);
+        table.save(&path).unwrap();
+        assert_eq!(BucketTable::load(&path).unwrap(), table);
+        // a table from a newer version is rejected
+        File::create(&path)
+            .unwrap()
+            .write_slice_to_file::<u32>(&[TABLE_MAGIC, TABLE_VERSION + 1, 1, 3, 0])
+            .unwrap();
+        assert!(BucketTable::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "indexer")]
+    fn test_lookup() {
+        let indexer = HandIndexer::init(2, [2, 3].to_vec());
+        let n_hands = indexer.size(1);
+        let table = BucketTable {
+            round: 1,
+            n_buckets: 7,
+            buckets: (0..n_hands).map(|i| (i % 7) as u32).collect(),
+        };
+        let mut hand = [0u8; 5];
+        for idx in [0, 1, 400, n_hands - 1].iter() {
+            indexer.get_hand(1, *idx, &mut hand);
+            assert_eq!(table.lookup(&indexer, &hand).ok(), table.bucket(*idx));
+        }
+        // missing, repeated or unknown cards are rejected before indexing
+        assert!(matches!(
+            table.lookup(&indexer, &hand[..4]),
+            Err(AbstractionError::InvalidCards(5))
+        ));
+        assert!(table.lookup(&indexer, &[0, 1, 2, 3, 3]).is_err());
+        assert!(table.lookup(&indexer, &[0, 1, 2, 3, 52]).is_err());
+        // a preflop table can't be used with a flop indexer
+        let preflop = BucketTable { round: 0, ..table };
+        assert!(matches!(
+            preflop.lookup(&indexer, &hand),
+            Err(AbstractionError::RoundMismatch)
+        ));
+    }
+
+    #[bench]
+    fn bench_turn_histogram(b: &mut Bencher) {
+        let mut hand = cards("AhKh");
+        hand.extend(cards("Qh7h2c3d"));
+        let mut rng = SmallRng::seed_from_u64(1);
+        b.iter(|| equity_histogram(&hand, 50, None, &mut rng).unwrap());
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::ops::Sub;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hand_range::{Combo, HandRange};
+use crate::range_filter::{get_made_hand_category, MadeHandCategories};
+
+/// Weighted number of combos left in a range after card removal
+///
+/// A combo with weight 100 counts as one combo, a combo with weight 50 as half a combo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComboCounts {
+    /// Total weighted combos
+    pub total: f64,
+    /// Weighted combos of each preflop hand class, e.g. "AKs"
+    pub by_class: BTreeMap<String, f64>,
+    /// Weighted combos of each made hand category on the board,
+    /// indexed by `MadeHandCategories::get_table_index`
+    pub by_made_hand: Vec<f64>,
+}
+
+impl ComboCounts {
+    fn new() -> Self {
+        ComboCounts {
+            total: 0.0,
+            by_class: BTreeMap::new(),
+            by_made_hand: vec![0.0; MadeHandCategories::category_count()],
+        }
+    }
+
+    fn add(&mut self, combo: &Combo, board_mask: u64) {
+        let count = f64::from(combo.2) / 100.0;
+        self.total += count;
+        *self.by_class.entry(combo.hand_class()).or_insert(0.0) += count;
+        let category = get_made_hand_category(combo, board_mask);
+        self.by_made_hand[category.get_table_index()] += count;
+    }
+
+    /// Get weighted combos of a preflop hand class
+    pub fn class(&self, class: &str) -> f64 {
+        *self.by_class.get(class).unwrap_or(&0.0)
+    }
+
+    /// Get weighted combos of a made hand category
+    pub fn made_hand(&self, category: MadeHandCategories) -> f64 {
+        self.by_made_hand[category.get_table_index()]
+    }
+}
+
+impl Sub for &ComboCounts {
+    type Output = ComboCounts;
+
+    fn sub(self, other: &ComboCounts) -> ComboCounts {
+        let mut counts = self.clone();
+        counts.total -= other.total;
+        for (class, count) in &other.by_class {
+            *counts.by_class.entry(class.clone()).or_insert(0.0) -= count;
+        }
+        counts.by_class.retain(|_, count| *count != 0.0);
+        for (a, b) in counts
+            .by_made_hand
+            .iter_mut()
+            .zip(other.by_made_hand.iter())
+        {
+            *a -= b;
+        }
+        counts
+    }
+}
+
+impl HandRange {
+    /// Count the weighted combos that remain after removing board and dead cards
+    ///
+    /// # Arguments
+    ///
+    /// * `board_mask` - 64 bit mask of public cards, used for made hand categories
+    /// * `dead_mask` - 64 bit mask of other cards out of play
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::{HandRange, get_card_mask};
+    /// let range = HandRange::from_string("AA,AKs@50".to_string());
+    /// let counts = range.combo_counts(get_card_mask("As7d2c"), 0);
+    /// assert_eq!(counts.class("AA"), 3.0);
+    /// assert_eq!(counts.class("AKs"), 1.5);
+    /// ```
+    pub fn combo_counts(&self, board_mask: u64, dead_mask: u64) -> ComboCounts {
+        let removed = board_mask | dead_mask;
+        let mut counts = ComboCounts::new();
+        for combo in &self.hands {
+            if (combo.mask() & removed) == 0 {
+                counts.add(combo, board_mask);
+            }
+        }
+        counts
+    }
+
+    /// Count the weighted combos that a hero holding removes from the range
+    ///
+    /// Combos already removed by board or dead cards are not counted
+    ///
+    /// # Arguments
+    ///
+    /// * `hero_mask` - 64 bit mask of hero's hole cards
+    /// * `board_mask` - 64 bit mask of public cards
+    /// * `dead_mask` - 64 bit mask of other cards out of play
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::{HandRange, get_card_mask};
+    /// let villain = HandRange::from_string("AA,KK,AKs@50".to_string());
+    /// let blocked = villain.blocked_combos(get_card_mask("AsKs"), 0, 0);
+    /// // 3 AA, 3 KK and 1 AKs at half weight
+    /// assert_eq!(blocked.total, 6.5);
+    /// ```
+    pub fn blocked_combos(&self, hero_mask: u64, board_mask: u64, dead_mask: u64) -> ComboCounts {
+        let before = self.combo_counts(board_mask, dead_mask);
+        let after = self.combo_counts(board_mask, dead_mask | hero_mask);
+        &before - &after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_combo_counts() {
+        let range = HandRange::from_string("22+,AKs@50,AKo".to_string());
+        let counts = range.combo_counts(0, 0);
+        assert_eq!(counts.total, 78.0 + 2.0 + 12.0);
+        assert_eq!(counts.class("AKo"), 12.0);
+        assert_eq!(counts.class("72o"), 0.0);
+        // board and dead cards both remove combos
+        let counts = range.combo_counts(get_card_mask("Ah7d2c"), get_card_mask("Ad"));
+        assert_eq!(counts.class("AA"), 1.0);
+        assert_eq!(counts.class("22"), 3.0);
+        assert_eq!(counts.class("AKs"), 1.0);
+        assert_eq!(counts.class("AKo"), 6.0);
+        assert_eq!(counts.made_hand(MadeHandCategories::ThreeOfAKind), 7.0);
+    }
+
+    #[test]
+    fn test_blocked_combos() {
+        let value = HandRange::from_string("QQ+,AKs,AKo@50".to_string());
+        let blocked = value.blocked_combos(get_card_mask("AsKs"), 0, 0);
+        assert_eq!(blocked.class("AA"), 3.0);
+        assert_eq!(blocked.class("KK"), 3.0);
+        assert_eq!(blocked.class("QQ"), 0.0);
+        assert_eq!(blocked.class("AKs"), 1.0);
+        assert_eq!(blocked.class("AKo"), 3.0);
+        assert_eq!(blocked.total, 10.0);
+        // cards on the board are not counted twice
+        let blocked = value.blocked_combos(get_card_mask("AsKs"), get_card_mask("Ah"), 0);
+        assert_eq!(blocked.class("AA"), 2.0);
+    }
+}
@@ -6,6 +6,9 @@ pub const CARD_COUNT: u8 = 52;
 pub const RANK_COUNT: u8 = 13;
 pub const SUIT_COUNT: u8 = 4;
 
+/// Cards left out of a 36 card short deck, deuces to fives
+pub const SHORT_DECK_REMOVED_CARDS: u64 = 0xffff;
+
 /// char to u8 rank table
 pub const RANK_TO_CHAR: &[char; 13] = &[
     '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
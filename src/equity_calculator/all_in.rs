@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+use super::simulator::{
+    approx_equity_with_options, exact_equity_with_options, EquityOptions, SimulatorError,
+};
+use super::EquityCalculation;
+use crate::hand_range::HandRange;
+
+/// A main or side pot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pot {
+    /// Chips in the pot
+    pub amount: f64,
+    /// Indexes of the players who can win the pot
+    pub players: Vec<usize>,
+    /// Share of the pot each player wins on average, indexed by player
+    pub equities: Vec<f64>,
+}
+
+/// Expected chips of every player in an all-in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllInEv {
+    /// Main pot first, then each side pot
+    pub pots: Vec<Pot>,
+    /// Chips each player expects to get back from the pots
+    pub ev: Vec<f64>,
+    /// Expected chips won or lost by each player, `ev` minus the amount put in
+    pub net: Vec<f64>,
+}
+
+/// Split the players' all-in amounts into a main pot and side pots
+///
+/// `pot` is dead money that goes to the main pot.
+/// Chips nobody else matched end up in a pot with one player,
+/// which returns them to that player
+fn build_pots(amounts: &[f64], pot: f64) -> Vec<Pot> {
+    let mut levels: Vec<f64> = amounts.iter().cloned().filter(|a| *a > 0.0).collect();
+    levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    levels.dedup();
+
+    let mut pots = Vec::new();
+    let mut prev = 0f64;
+    for level in levels {
+        let players: Vec<usize> = (0..amounts.len())
+            .filter(|i| amounts[*i] >= level)
+            .collect();
+        let amount = (level - prev) * players.len() as f64;
+        prev = level;
+        pots.push(Pot {
+            amount,
+            players,
+            equities: vec![0f64; amounts.len()],
+        });
+    }
+    match pots.first_mut() {
+        Some(main) => main.amount += pot,
+        None => pots.push(Pot {
+            amount: pot,
+            players: (0..amounts.len()).collect(),
+            equities: vec![0f64; amounts.len()],
+        }),
+    }
+    pots
+}
+
+/// Calculates each player's expected chips when everyone is all in
+///
+/// Every player is dealt a hand for every pot, so the cards of players
+/// who can't win a side pot are out of the deck.
+///
+/// # Arguments
+///
+/// * `hand_ranges` Array of hand ranges
+/// * `amounts` Chips each player put in, capped by their stack
+/// * `pot` Chips already in the pot before the all-in
+/// * `options` Board, dead cards, threads and monte carlo stopping rules
+/// * `exact` Enumerate every runout instead of sampling
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{all_in_ev, EquityOptions};
+/// let ranges = HandRange::from_strings(
+///     ["AhAd".to_string(), "KsKc".to_string(), "QsQh".to_string()].to_vec(),
+/// );
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("7c5d2h"),
+///     ..EquityOptions::default()
+/// };
+/// // the short stack can only win the main pot
+/// let result = all_in_ev(&ranges, &[20.0, 100.0, 100.0], 10.0, &options, true).unwrap();
+/// assert_eq!(result.pots.len(), 2);
+/// assert_eq!(result.pots[0].amount, 70.0);
+/// assert_eq!(result.pots[1].players, vec![1, 2]);
+/// let total: f64 = result.ev.iter().sum();
+/// assert!((total - 230.0).abs() < 1e-6);
+/// ```
+pub fn all_in_ev(
+    hand_ranges: &[HandRange],
+    amounts: &[f64],
+    pot: f64,
+    options: &EquityOptions,
+    exact: bool,
+) -> Result<AllInEv, SimulatorError> {
+    let valid = |a: f64| a.is_finite() && a >= 0.0;
+    if amounts.len() != hand_ranges.len() || !amounts.iter().all(|a| valid(*a)) || !valid(pot) {
+        return Err(SimulatorError::InvalidAmounts);
+    }
+
+    let mut pots = build_pots(amounts, pot);
+    for p in &mut pots {
+        if p.players.len() == 1 {
+            p.equities[p.players[0]] = 1.0;
+            continue;
+        }
+        if p.players.len() == hand_ranges.len() {
+            let result = if exact {
+                exact_equity_with_options(hand_ranges, options)?
+            } else {
+                approx_equity_with_options(hand_ranges, options)?
+            };
+            p.equities = result.equities;
+            continue;
+        }
+        // players who can win the pot go first, so the stdev target applies to one of them
+        let order: Vec<usize> = p
+            .players
+            .iter()
+            .cloned()
+            .chain((0..hand_ranges.len()).filter(|i| !p.players.contains(i)))
+            .collect();
+        let ranges: Vec<HandRange> = order.iter().map(|i| hand_ranges[*i].clone()).collect();
+        let contenders = (1 << p.players.len()) - 1;
+        let result =
+            EquityCalculation::start_contested(&ranges, options, exact, contenders)?.wait();
+        for (i, e) in order.iter().zip(result.equities) {
+            p.equities[*i] = e;
+        }
+    }
+
+    let ev: Vec<f64> = (0..amounts.len())
+        .map(|i| pots.iter().map(|p| p.amount * p.equities[i]).sum())
+        .collect();
+    let net = ev.iter().zip(amounts).map(|(e, a)| e - a).collect();
+    Ok(AllInEv { pots, ev, net })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_build_pots() {
+        let pots = build_pots(&[50.0, 200.0, 100.0, 200.0], 30.0);
+        let amounts: Vec<f64> = pots.iter().map(|p| p.amount).collect();
+        assert_eq!(amounts, vec![230.0, 150.0, 200.0]);
+        assert_eq!(pots[0].players, vec![0, 1, 2, 3]);
+        assert_eq!(pots[1].players, vec![1, 2, 3]);
+        assert_eq!(pots[2].players, vec![1, 3]);
+        // an unmatched bet goes back to the player
+        let pots = build_pots(&[50.0, 80.0], 0.0);
+        assert_eq!(pots[1].amount, 30.0);
+        assert_eq!(pots[1].players, vec![1]);
+    }
+
+    #[test]
+    fn test_heads_up_ev() {
+        let ranges = HandRange::from_strings(["AhAd".to_string(), "KsKc".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Kh7c2d"),
+            ..EquityOptions::default()
+        };
+        let equities = exact_equity_with_options(&ranges, &options)
+            .unwrap()
+            .equities;
+        let result = all_in_ev(&ranges, &[100.0, 150.0], 20.0, &options, true).unwrap();
+        assert!((result.ev[0] - 220.0 * equities[0]).abs() < 1e-9);
+        assert!((result.ev[1] - 220.0 * equities[1] - 50.0).abs() < 1e-9);
+        assert!((result.net[0] + result.net[1] - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_side_pot_equity() {
+        // the short stack wins the main pot and KK the side pot
+        let ranges = HandRange::from_strings(
+            ["AsAh".to_string(), "KsKh".to_string(), "QdJd".to_string()].to_vec(),
+        );
+        let options = EquityOptions {
+            board_mask: get_card_mask("AdAc7s2h3c"),
+            ..EquityOptions::default()
+        };
+        let result = all_in_ev(&ranges, &[10.0, 50.0, 50.0], 0.0, &options, true).unwrap();
+        assert!((result.ev[0] - 30.0).abs() < 1e-9);
+        assert!((result.ev[1] - 80.0).abs() < 1e-9);
+        assert!(result.ev[2].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_side_pot_card_removal() {
+        // AhAd takes two of KK's outs away from QQ in the side pot
+        let ranges = HandRange::from_strings(
+            ["AhAd".to_string(), "KsKc".to_string(), "QsQh".to_string()].to_vec(),
+        );
+        let options = EquityOptions {
+            board_mask: get_card_mask("7c5d2h"),
+            ..EquityOptions::default()
+        };
+        let result = all_in_ev(&ranges, &[20.0, 100.0, 100.0], 0.0, &options, true).unwrap();
+        let side_pot = &result.pots[1];
+        assert_eq!(side_pot.players, vec![1, 2]);
+        let heads_up = exact_equity_with_options(&ranges[1..], &options).unwrap();
+        let with_aces_dead = exact_equity_with_options(
+            &ranges[1..],
+            &EquityOptions {
+                dead_mask: get_card_mask("AhAd"),
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert!((side_pot.equities[1] - with_aces_dead.equities[0]).abs() < 1e-9);
+        assert!((side_pot.equities[1] - heads_up.equities[0]).abs() > 1e-3);
+        assert_eq!(side_pot.equities[0], 0.0);
+        // the monte carlo estimate removes the same cards
+        let approx_options = EquityOptions {
+            stdev_target: 0.001,
+            seed: Some(5),
+            ..options
+        };
+        let approx =
+            all_in_ev(&ranges, &[20.0, 100.0, 100.0], 0.0, &approx_options, false).unwrap();
+        assert!((approx.pots[1].equities[1] - with_aces_dead.equities[0]).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_invalid_amounts() {
+        let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+        let options = EquityOptions::default();
+        assert!(matches!(
+            all_in_ev(&ranges, &[10.0], 0.0, &options, false),
+            Err(SimulatorError::InvalidAmounts)
+        ));
+        assert!(matches!(
+            all_in_ev(&ranges, &[10.0, -1.0], 0.0, &options, false),
+            Err(SimulatorError::InvalidAmounts)
+        ));
+    }
+}
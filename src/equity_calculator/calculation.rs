@@ -0,0 +1,440 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossbeam::channel::Receiver;
+use serde::{Deserialize, Serialize};
+
+use super::simulator::{EquityOptions, Simulator, SimulatorError};
+use super::EquityResult;
+use crate::hand_range::HandRange;
+
+/// Snapshot of a running equity calculation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Progress {
+    /// Preflop combos handed out to threads, exact calculations only
+    pub position: u64,
+    /// Total preflop combos to enumerate, zero for monte carlo
+    pub total: u64,
+    /// Estimated fraction of the calculation done, from 0 to 1
+    ///
+    /// Monte carlo calculations estimate this from the standard error
+    /// and the target standard deviation
+    pub fraction: f64,
+    /// Number of hands evaluated so far
+    pub eval_count: u64,
+    /// Current equity estimate of each player
+    pub equities: Vec<f64>,
+    /// Current standard error of each player's equity
+    pub stdev: Vec<f64>,
+}
+
+/// A unit of work run by an `EquityEngine`
+pub(super) type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Counts the work units of a calculation that haven't finished
+#[derive(Debug)]
+struct UnitLatch {
+    remaining: Mutex<usize>,
+    finished: Condvar,
+}
+
+impl UnitLatch {
+    fn done(&self) {
+        let mut remaining = self.remaining.lock().unwrap();
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.finished.notify_all();
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        *self.remaining.lock().unwrap() == 0
+    }
+
+    fn wait(&self) {
+        let mut remaining = self.remaining.lock().unwrap();
+        while *remaining > 0 {
+            remaining = self.finished.wait(remaining).unwrap();
+        }
+    }
+}
+
+/// Marks a work unit finished when dropped, even if the unit panics
+struct UnitGuard(Arc<UnitLatch>);
+
+impl Drop for UnitGuard {
+    fn drop(&mut self) {
+        self.0.done();
+    }
+}
+
+/// Threads working on a calculation
+#[derive(Debug)]
+enum Workers {
+    /// Threads started for this calculation
+    Threads(Vec<JoinHandle<()>>),
+    /// Work units submitted to shared workers
+    Units(Arc<UnitLatch>),
+}
+
+/// An equity calculation running on background threads
+///
+/// Dropping the calculation cancels it
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use rust_poker::hand_range::HandRange;
+/// use rust_poker::equity_calculator::{EquityCalculation, EquityOptions};
+/// let ranges = HandRange::from_strings(["AK".to_string(), "22+".to_string()].to_vec());
+/// let calc = EquityCalculation::start_exact(&ranges, &EquityOptions::default()).unwrap();
+/// let result = calc.wait_with_progress(Duration::from_millis(10), |progress| {
+///     println!("{:.1}% {:?}", 100.0 * progress.fraction, progress.equities);
+///     true
+/// });
+/// assert!(result.exact);
+/// ```
+#[derive(Debug)]
+pub struct EquityCalculation {
+    sim: Arc<Simulator>,
+    workers: Workers,
+}
+
+impl EquityCalculation {
+    /// Start enumerating every runout of every matchup
+    ///
+    /// # Arguments
+    ///
+    /// * `hand_ranges` Array of hand ranges
+    /// * `options` Board, thread count and which results to calculate
+    pub fn start_exact(
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+    ) -> Result<Self, SimulatorError> {
+        let sim = Simulator::prepare(hand_ranges, options, true)?;
+        Ok(EquityCalculation::start_threads(
+            sim,
+            options.n_threads,
+            Simulator::enumerate_all,
+        ))
+    }
+
+    /// Start a monte carlo simulation
+    ///
+    /// # Arguments
+    ///
+    /// * `hand_ranges` Array of hand ranges
+    /// * `options` Board, thread count, stdev target and which results to calculate
+    pub fn start_approx(
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+    ) -> Result<Self, SimulatorError> {
+        let sim = Simulator::prepare(hand_ranges, options, false)?;
+        Ok(EquityCalculation::start_threads(
+            sim,
+            options.n_threads,
+            Simulator::sim_random_walk_monte_carlo,
+        ))
+    }
+
+    /// Start a calculation where only some players can win
+    ///
+    /// The other players are still dealt a hand, so their cards are out of the deck
+    ///
+    /// # Arguments
+    ///
+    /// * `hand_ranges` Array of hand ranges
+    /// * `options` Board, thread count, stdev target and which results to calculate
+    /// * `exact` Enumerate every runout instead of running a monte carlo simulation
+    /// * `contenders` Bit mask of the players who can win
+    pub(super) fn start_contested(
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+        exact: bool,
+        contenders: u32,
+    ) -> Result<Self, SimulatorError> {
+        let mut sim = Simulator::prepare(hand_ranges, options, exact)?;
+        sim.set_contenders(contenders);
+        let run = if exact {
+            Simulator::enumerate_all
+        } else {
+            Simulator::sim_random_walk_monte_carlo
+        };
+        Ok(EquityCalculation::start_threads(
+            sim,
+            options.n_threads,
+            run,
+        ))
+    }
+
+    /// Run a prepared simulator on threads of its own
+    fn start_threads(sim: Simulator, n_threads: u8, run: fn(&Simulator)) -> Self {
+        let sim = Arc::new(sim);
+        sim.set_worker_count(usize::from(n_threads));
+        let handles = (0..n_threads)
+            .map(|_| {
+                let sim = Arc::clone(&sim);
+                thread::spawn(move || sim.run_worker(run))
+            })
+            .collect();
+        EquityCalculation {
+            sim,
+            workers: Workers::Threads(handles),
+        }
+    }
+
+    /// Start a calculation as work units on shared workers
+    ///
+    /// # Arguments
+    ///
+    /// * `sim` Prepared simulator
+    /// * `n_units` Number of work units to split the calculation into
+    /// * `run` Work done by each unit
+    /// * `submit` Hands a unit to a worker
+    pub(super) fn start_units<S>(
+        sim: Simulator,
+        n_units: usize,
+        run: fn(&Simulator),
+        submit: S,
+    ) -> Self
+    where
+        S: Fn(Job),
+    {
+        let sim = Arc::new(sim);
+        sim.set_worker_count(n_units);
+        let latch = Arc::new(UnitLatch {
+            remaining: Mutex::new(n_units),
+            finished: Condvar::new(),
+        });
+        for _ in 0..n_units {
+            let sim = Arc::clone(&sim);
+            let guard = UnitGuard(Arc::clone(&latch));
+            submit(Box::new(move || {
+                let _guard = guard;
+                sim.run_worker(run);
+            }));
+        }
+        EquityCalculation {
+            sim,
+            workers: Workers::Units(latch),
+        }
+    }
+
+    /// Get the current progress and equity estimates
+    pub fn progress(&self) -> Progress {
+        self.sim.progress()
+    }
+
+    /// Get a channel that receives estimates while the calculation runs
+    ///
+    /// An estimate is sent when results are merged, at most once every `interval`.
+    /// The last estimate is sent when every thread has finished, then the channel closes,
+    /// so iterating over the receiver ends with the calculation
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use rust_poker::hand_range::HandRange;
+    /// use rust_poker::equity_calculator::{EquityCalculation, EquityOptions};
+    /// let ranges = HandRange::from_strings(["AK".to_string(), "22+".to_string()].to_vec());
+    /// let options = EquityOptions {
+    ///     stdev_target: 0.0005,
+    ///     ..EquityOptions::default()
+    /// };
+    /// let calc = EquityCalculation::start_approx(&ranges, &options).unwrap();
+    /// for estimate in calc.subscribe(Duration::from_millis(20)) {
+    ///     let error = 1.96 * estimate.stdev[0];
+    ///     println!("{:.3} +- {:.3}", estimate.equities[0], error);
+    /// }
+    /// let result = calc.wait();
+    /// ```
+    pub fn subscribe(&self, interval: Duration) -> Receiver<Progress> {
+        self.sim.subscribe(interval)
+    }
+
+    /// Get the results so far without stopping the calculation
+    pub fn estimate(&self) -> EquityResult {
+        let mut result = self.sim.get_result();
+        result.exact = result.exact && self.is_finished();
+        result
+    }
+
+    /// Ask all threads to stop
+    ///
+    /// `wait` still returns the results gathered before the threads stopped
+    pub fn cancel(&self) {
+        self.sim.cancel();
+    }
+
+    /// Was the calculation cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.sim.is_cancelled()
+    }
+
+    /// Is the calculation an exact enumeration
+    pub fn is_exact(&self) -> bool {
+        self.sim.is_exact()
+    }
+
+    /// Have all threads finished
+    pub fn is_finished(&self) -> bool {
+        match &self.workers {
+            Workers::Threads(handles) => handles.iter().all(|h| h.is_finished()),
+            Workers::Units(latch) => latch.is_done(),
+        }
+    }
+
+    /// Block until all threads have finished and return the results
+    pub fn wait(mut self) -> EquityResult {
+        match &mut self.workers {
+            Workers::Threads(handles) => {
+                for handle in std::mem::take(handles) {
+                    handle.join().unwrap();
+                }
+            }
+            Workers::Units(latch) => latch.wait(),
+        }
+        self.sim.get_result()
+    }
+
+    /// Block until all threads have finished, calling `callback` with the progress
+    /// every `interval`
+    ///
+    /// The calculation is cancelled if the callback returns false
+    pub fn wait_with_progress<F>(self, interval: Duration, mut callback: F) -> EquityResult
+    where
+        F: FnMut(&Progress) -> bool,
+    {
+        while !self.is_finished() {
+            thread::sleep(interval);
+            if !callback(&self.progress()) {
+                self.cancel();
+                break;
+            }
+        }
+        self.wait()
+    }
+}
+
+impl Drop for EquityCalculation {
+    fn drop(&mut self) {
+        if !self.is_finished() {
+            self.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equity_calculator::StopReason;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_cancel_exact() {
+        let ranges = HandRange::from_strings(
+            [
+                "random".to_string(),
+                "random".to_string(),
+                "random".to_string(),
+            ]
+            .to_vec(),
+        );
+        let options = EquityOptions {
+            n_threads: 2,
+            ..EquityOptions::default()
+        };
+        let calc = EquityCalculation::start_exact(&ranges, &options).unwrap();
+        assert!(calc.is_exact());
+        thread::sleep(Duration::from_millis(50));
+        let progress = calc.progress();
+        assert!(progress.total > 0);
+        assert!(progress.fraction < 1.0);
+        calc.cancel();
+        let result = calc.wait();
+        assert!(!result.exact);
+        assert_eq!(result.stop_reason, StopReason::Cancelled);
+        assert!(result.eval_count > 0);
+        let equity: f64 = result.equities.iter().sum();
+        assert!((equity - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_progress_callback() {
+        let ranges = HandRange::from_strings(["QQ".to_string(), "AKs".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Jh8c3d"),
+            stdev_target: 1e-9,
+            ..EquityOptions::default()
+        };
+        let calc = EquityCalculation::start_approx(&ranges, &options).unwrap();
+        let mut calls = 0;
+        let result = calc.wait_with_progress(Duration::from_millis(5), |progress| {
+            calls += 1;
+            assert_eq!(progress.total, 0);
+            calls < 3
+        });
+        assert_eq!(calls, 3);
+        assert!(!result.exact);
+        assert!(result.equities[0] > 0.6 && result.equities[0] < 0.8);
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let ranges = HandRange::from_strings(["QQ".to_string(), "AKs".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Jh8c3d"),
+            stdev_target: 0.0002,
+            seed: Some(4),
+            ..EquityOptions::default()
+        };
+        let calc = EquityCalculation::start_approx(&ranges, &options).unwrap();
+        let estimates: Vec<Progress> = calc.subscribe(Duration::from_millis(1)).iter().collect();
+        assert!(estimates.len() > 1);
+        // estimates arrive while sampling, the last one is the final result
+        let first = &estimates[0];
+        let last = estimates.last().unwrap();
+        assert!(last.eval_count > first.eval_count);
+        assert!(last.stdev[0] < options.stdev_target);
+        assert!(calc.is_finished());
+        let result = calc.wait();
+        assert_eq!(last.equities, result.equities);
+        assert_eq!(last.eval_count, result.eval_count);
+    }
+
+    #[test]
+    fn test_subscribe_after_finish() {
+        let ranges = HandRange::from_strings(["AsKd".to_string(), "AhKc,QQ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c3d4h5sQd"),
+            ..EquityOptions::default()
+        };
+        let calc = EquityCalculation::start_exact(&ranges, &options).unwrap();
+        while !calc.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let estimates: Vec<Progress> = calc.subscribe(Duration::from_secs(1)).iter().collect();
+        assert_eq!(estimates.len(), 1);
+        assert_eq!(estimates[0].fraction, 1.0);
+    }
+
+    #[test]
+    fn test_finished_exact() {
+        let ranges = HandRange::from_strings(["AsKd".to_string(), "AhKc,QQ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c3d4h5sQd"),
+            ..EquityOptions::default()
+        };
+        let calc = EquityCalculation::start_exact(&ranges, &options).unwrap();
+        while !calc.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(calc.progress().fraction, 1.0);
+        assert!(!calc.is_cancelled());
+        let result = calc.wait();
+        assert!(result.exact);
+        assert_eq!(result.stop_reason, StopReason::Completed);
+        assert!((result.equities[0] - 0.875).abs() < 1e-9);
+    }
+}
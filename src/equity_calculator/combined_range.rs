@@ -1,31 +1,33 @@
+use super::simulator::MAX_PLAYERS;
 use crate::hand_evaluator::Hand;
 use crate::hand_range::HandRange;
 use rand::seq::SliceRandom;
 use rand::Rng;
-
-/// Max player count
-const MAX_PLAYERS: usize = 6;
+use smallvec::{smallvec, SmallVec};
 
 /// Max combined range size
 const MAX_SIZE: usize = 10000;
 
-#[derive(Debug, Copy, Clone)]
+/// Players stored inline in a combo, most combined ranges hold one or two
+const INLINE_PLAYERS: usize = 2;
+
+#[derive(Debug, Clone)]
 /// One valid combination of hole card hands
 pub struct Combo {
     /// Mask of all cards in combo used for rejection sampling
     pub mask: u64,
-    /// Option vector of hands
-    pub hands: [Hand; MAX_PLAYERS],
-    /// tuple of (card_idx, card_idx, hand_weight)
-    pub hole_cards: [(u8, u8, u8); MAX_PLAYERS],
+    /// Hand of each player in the combined range
+    pub hands: SmallVec<[Hand; INLINE_PLAYERS]>,
+    /// tuple of (card_idx, card_idx, hand_weight) for each player in the combined range
+    pub hole_cards: SmallVec<[(u8, u8, f32); INLINE_PLAYERS]>,
 }
 
 impl Combo {
-    fn new() -> Self {
+    fn new(player_count: usize) -> Self {
         Combo {
             mask: 0,
-            hands: [Hand::default(); MAX_PLAYERS],
-            hole_cards: [(52, 52, 0); MAX_PLAYERS],
+            hands: smallvec![Hand::default(); player_count],
+            hole_cards: smallvec![(52, 52, 0.0); player_count],
         }
     }
 }
@@ -60,7 +62,7 @@ impl CombinedRange {
         c_range.player_count = 1;
         c_range.players[0] = player_idx;
         for r in &range.hands {
-            let mut c = Combo::new();
+            let mut c = Combo::new(1);
             c.mask = (1u64 << r.0) | (1u64 << r.1);
             c.hands[0] = Hand::from_hole_cards(r.0, r.1);
             c.hole_cards[0] = (r.0, r.1, r.2);
@@ -117,7 +119,7 @@ impl CombinedRange {
                 if (c1.mask & c2.mask) != 0 {
                     continue;
                 }
-                let mut combo = Combo::new();
+                let mut combo = Combo::new(c_range.player_count);
                 combo.mask = c1.mask | c2.mask;
                 for i in 0..self.player_count {
                     combo.hole_cards[i] = c1.hole_cards[i];
@@ -143,6 +145,10 @@ impl CombinedRange {
                     size += 1;
                 }
             }
+            // too big to join, no need for the exact size
+            if size >= MAX_SIZE as u64 {
+                break;
+            }
         }
         size
     }
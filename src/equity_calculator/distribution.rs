@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use super::simulator::{EquityOptions, SimulatorError};
+use super::{approx_equity_with_options, exact_equity_with_options, ComboEquity, EquityResult};
+use crate::hand_range::HandRange;
+
+/// Weighted distribution of the combo equities in a player's range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityDistribution {
+    /// Equity and weight of each combo, sorted by equity
+    points: Vec<(f64, f64)>,
+    /// Sum of the weights
+    total_weight: f64,
+}
+
+impl EquityDistribution {
+    /// Build the distribution from the combo equities of a player
+    pub fn from_combo_equities(combo_equities: &[ComboEquity]) -> Self {
+        let mut points: Vec<(f64, f64)> = combo_equities
+            .iter()
+            .filter(|c| c.weight > 0.0)
+            .map(|c| (c.equity, c.weight))
+            .collect();
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let total_weight = points.iter().map(|p| p.1).sum();
+        EquityDistribution {
+            points,
+            total_weight,
+        }
+    }
+
+    /// Whether no combo of the range has a positive weight
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Weighted mean equity, 0 for an empty range
+    pub fn mean(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.points.iter().map(|p| p.0 * p.1).sum::<f64>() / self.total_weight
+    }
+
+    /// Fraction of the range in each of `n_buckets` equal width equity buckets
+    ///
+    /// Bucket `i` holds equities in `[i / n_buckets, (i + 1) / n_buckets)`,
+    /// the last bucket also holds equity 1.
+    /// Returns no buckets if `n_buckets` is 0 and all zeros for an empty range
+    pub fn histogram(&self, n_buckets: usize) -> Vec<f64> {
+        let mut buckets = vec![0f64; n_buckets];
+        if n_buckets == 0 {
+            return buckets;
+        }
+        for (equity, weight) in &self.points {
+            let idx = ((equity * n_buckets as f64) as usize).min(n_buckets - 1);
+            buckets[idx] += weight / self.total_weight;
+        }
+        buckets
+    }
+
+    /// Smallest equity such that at least a fraction `p` of the range
+    /// has equal or lower equity
+    ///
+    /// # Arguments
+    ///
+    /// * `p` Fraction of the range from 0 to 1, 0.5 for the median
+    ///
+    /// Returns 0 for an empty range
+    pub fn percentile(&self, p: f64) -> f64 {
+        let target = p.clamp(0.0, 1.0) * self.total_weight;
+        let mut cumulative = 0f64;
+        for (equity, weight) in &self.points {
+            cumulative += weight;
+            // allow for rounding in the cumulative sum
+            if cumulative >= target - 1e-12 {
+                return *equity;
+            }
+        }
+        self.points.last().map_or(0.0, |p| p.0)
+    }
+
+    /// Equity of the range sorted from strongest to weakest, sampled at
+    /// `n_points` evenly spaced fractions of the range
+    ///
+    /// This is the equity curve drawn by range analysis tools
+    pub fn curve(&self, n_points: usize) -> Vec<f64> {
+        (0..n_points)
+            .map(|i| {
+                let p = if n_points > 1 {
+                    i as f64 / (n_points - 1) as f64
+                } else {
+                    0.0
+                };
+                self.percentile(1.0 - p)
+            })
+            .collect()
+    }
+}
+
+impl EquityResult {
+    /// Distribution of the combo equities of a player
+    ///
+    /// Returns `None` unless combo equities were calculated for `player`
+    pub fn equity_distribution(&self, player: usize) -> Option<EquityDistribution> {
+        self.combo_equities
+            .as_ref()?
+            .get(player)
+            .map(|c| EquityDistribution::from_combo_equities(c))
+    }
+}
+
+/// Calculates the distribution of a player's combo equities against the other ranges
+///
+/// Fails with `InvalidPlayer` if `player` is not an index into `hand_ranges`
+///
+/// # Arguments
+///
+/// * `hand_ranges` Array of hand ranges
+/// * `player` Index of the player whose range is broken down
+/// * `options` Board, thread count and stdev target
+/// * `exact` Enumerate every runout instead of running a monte carlo simulation
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{equity_distribution, EquityOptions};
+/// let ranges = HandRange::from_strings(["22+,AT+".to_string(), "QQ+,AK".to_string()].to_vec());
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Qs8d3c"),
+///     ..EquityOptions::default()
+/// };
+/// let dist = equity_distribution(&ranges, 0, &options, true).unwrap();
+/// let histogram = dist.histogram(10);
+/// assert!((histogram.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+/// assert!(dist.percentile(0.25) <= dist.percentile(0.75));
+/// ```
+pub fn equity_distribution(
+    hand_ranges: &[HandRange],
+    player: usize,
+    options: &EquityOptions,
+    exact: bool,
+) -> Result<EquityDistribution, SimulatorError> {
+    if player >= hand_ranges.len() {
+        return Err(SimulatorError::InvalidPlayer);
+    }
+    let options = EquityOptions {
+        combo_equity: true,
+        ..options.clone()
+    };
+    let result = if exact {
+        exact_equity_with_options(hand_ranges, &options)?
+    } else {
+        approx_equity_with_options(hand_ranges, &options)?
+    };
+    Ok(result.equity_distribution(player).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_distribution_stats() {
+        let ranges = HandRange::from_strings(["AA,KK,72o".to_string(), "QQ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c3d4h"),
+            ..EquityOptions::default()
+        };
+        let result = exact_equity_with_options(
+            &ranges,
+            &EquityOptions {
+                combo_equity: true,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        let dist = result.equity_distribution(0).unwrap();
+        assert!((dist.mean() - result.equities[0]).abs() < 1e-9);
+
+        // 72o is behind, the overpairs are ahead
+        let histogram = dist.histogram(4);
+        assert!(histogram[0] + histogram[1] > 0.0);
+        assert!(histogram[3] > 0.0);
+        assert!((histogram.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(dist.percentile(0.0) < 0.5);
+        assert!(dist.percentile(1.0) > 0.75);
+
+        let curve = dist.curve(11);
+        assert_eq!(curve[0], dist.percentile(1.0));
+        assert_eq!(curve[10], dist.percentile(0.0));
+        assert!(curve.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn test_distribution_approx() {
+        let ranges = HandRange::from_strings(["JJ+,AK".to_string(), "TT".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("9c5d2h"),
+            stdev_target: 0.002,
+            seed: Some(1),
+            ..EquityOptions::default()
+        };
+        let exact = equity_distribution(&ranges, 0, &options, true).unwrap();
+        let approx = equity_distribution(&ranges, 0, &options, false).unwrap();
+        assert!((exact.mean() - approx.mean()).abs() < 0.02);
+        assert!((exact.percentile(0.5) - approx.percentile(0.5)).abs() < 0.05);
+        assert!(exact_equity_with_options(&ranges, &options)
+            .unwrap()
+            .equity_distribution(0)
+            .is_none());
+    }
+
+    #[test]
+    fn test_distribution_empty() {
+        let ranges = HandRange::from_strings(["AA,KK".to_string(), "QQ".to_string()].to_vec());
+        let result = exact_equity_with_options(
+            &ranges,
+            &EquityOptions {
+                board_mask: get_card_mask("2c3d4h"),
+                combo_equity: true,
+                ..EquityOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(result
+            .equity_distribution(0)
+            .unwrap()
+            .histogram(0)
+            .is_empty());
+        // there is no third player
+        assert!(result.equity_distribution(2).is_none());
+        assert!(matches!(
+            equity_distribution(&ranges, 2, &EquityOptions::default(), true),
+            Err(SimulatorError::InvalidPlayer)
+        ));
+
+        // only zero weight combos
+        let mut combos = result.combo_equities.unwrap()[0].clone();
+        combos.iter_mut().for_each(|c| c.weight = 0.0);
+        for dist in &[
+            EquityDistribution::from_combo_equities(&combos),
+            EquityDistribution::from_combo_equities(&[]),
+        ] {
+            assert!(dist.is_empty());
+            assert_eq!(dist.mean(), 0.0);
+            assert_eq!(dist.histogram(4), vec![0.0; 4]);
+            assert_eq!(dist.percentile(0.5), 0.0);
+            assert_eq!(dist.curve(3), vec![0.0; 3]);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "rayon")]
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crossbeam::channel::{self, Sender};
+
+use super::calculation::Job;
+use super::preflop_table;
+use super::simulator::{EquityOptions, Simulator, SimulatorError};
+use super::{EquityCalculation, EquityResult};
+use crate::hand_range::HandRange;
+
+/// Where an engine runs work units
+enum Executor {
+    /// Threads owned by the engine
+    Pool {
+        sender: Option<Sender<Job>>,
+        threads: Vec<JoinHandle<()>>,
+    },
+    /// A rayon thread pool shared with the rest of the program
+    #[cfg(feature = "rayon")]
+    Rayon(Arc<rayon::ThreadPool>),
+}
+
+/// Runs equity calculations on long lived worker threads
+///
+/// Starting threads for every calculation dominates the cost of small queries.
+/// An engine starts its workers once and splits each query into
+/// `options.n_threads` work units that share them, so many queries
+/// can run at the same time.
+///
+/// Don't wait on a query from inside a work unit of the same engine,
+/// the unit would block a worker the query may need.
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{EquityEngine, EquityOptions};
+/// let engine = EquityEngine::new(4);
+/// let ranges = HandRange::from_strings(["AK".to_string(), "QQ".to_string()].to_vec());
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Kh7c2d"),
+///     ..EquityOptions::default()
+/// };
+/// for _ in 0..10 {
+///     let result = engine.exact_equity(&ranges, &options).unwrap();
+///     assert!(result.equities[0] > 0.5);
+/// }
+/// ```
+pub struct EquityEngine {
+    executor: Executor,
+}
+
+impl EquityEngine {
+    /// Start an engine with its own worker threads
+    ///
+    /// # Arguments
+    ///
+    /// * `n_threads` Number of worker threads, at least one is started
+    pub fn new(n_threads: usize) -> Self {
+        let (sender, receiver) = channel::unbounded::<Job>();
+        let threads = (0..n_threads.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || {
+                    for job in receiver.iter() {
+                        // a panicking unit must not take the worker down with it
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                })
+            })
+            .collect();
+        EquityEngine {
+            executor: Executor::Pool {
+                sender: Some(sender),
+                threads,
+            },
+        }
+    }
+
+    /// Create an engine that runs work units on a rayon thread pool
+    #[cfg(feature = "rayon")]
+    pub fn with_rayon_pool(pool: Arc<rayon::ThreadPool>) -> Self {
+        EquityEngine {
+            executor: Executor::Rayon(pool),
+        }
+    }
+
+    /// Number of worker threads
+    pub fn n_threads(&self) -> usize {
+        match &self.executor {
+            Executor::Pool { threads, .. } => threads.len(),
+            #[cfg(feature = "rayon")]
+            Executor::Rayon(pool) => pool.current_num_threads(),
+        }
+    }
+
+    fn submit(&self, job: Job) {
+        match &self.executor {
+            Executor::Pool { sender, .. } => sender.as_ref().unwrap().send(job).unwrap(),
+            #[cfg(feature = "rayon")]
+            Executor::Rayon(pool) => pool.spawn(job),
+        }
+    }
+
+    fn start(
+        &self,
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+        exact: bool,
+    ) -> Result<EquityCalculation, SimulatorError> {
+        let sim = Simulator::prepare(hand_ranges, options, exact)?;
+        let run = if exact {
+            Simulator::enumerate_all
+        } else {
+            Simulator::sim_random_walk_monte_carlo
+        };
+        let n_units = usize::from(options.n_threads.max(1));
+        Ok(EquityCalculation::start_units(sim, n_units, run, |job| {
+            self.submit(job)
+        }))
+    }
+
+    /// Start enumerating every runout of every matchup on the engine's workers
+    ///
+    /// # Arguments
+    ///
+    /// * `hand_ranges` Array of hand ranges
+    /// * `options` Board, number of work units and which results to calculate
+    pub fn start_exact(
+        &self,
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+    ) -> Result<EquityCalculation, SimulatorError> {
+        self.start(hand_ranges, options, true)
+    }
+
+    /// Start a monte carlo simulation on the engine's workers
+    ///
+    /// # Arguments
+    ///
+    /// * `hand_ranges` Array of hand ranges
+    /// * `options` Board, number of work units, stdev target and which results to calculate
+    pub fn start_approx(
+        &self,
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+    ) -> Result<EquityCalculation, SimulatorError> {
+        self.start(hand_ranges, options, false)
+    }
+
+    /// Calculates exact range vs range equities, same as `exact_equity_with_options`
+    pub fn exact_equity(
+        &self,
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+    ) -> Result<EquityResult, SimulatorError> {
+        if let Some(result) = preflop_table::lookup(hand_ranges, options) {
+            return Ok(result);
+        }
+        Ok(self.start_exact(hand_ranges, options)?.wait())
+    }
+
+    /// Runs a monte carlo simulation, same as `approx_equity_with_options`
+    pub fn approx_equity(
+        &self,
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+    ) -> Result<EquityResult, SimulatorError> {
+        Ok(self.start_approx(hand_ranges, options)?.wait())
+    }
+}
+
+impl Drop for EquityEngine {
+    fn drop(&mut self) {
+        match &mut self.executor {
+            Executor::Pool { sender, threads } => {
+                // closing the channel ends the workers once the queue is empty
+                sender.take();
+                for thread in threads.drain(..) {
+                    let _ = thread.join();
+                }
+            }
+            #[cfg(feature = "rayon")]
+            Executor::Rayon(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equity_calculator::{exact_equity_with_options, StopReason};
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_engine_matches_exact() {
+        let engine = EquityEngine::new(3);
+        assert_eq!(engine.n_threads(), 3);
+        let ranges = HandRange::from_strings(["AK,TT".to_string(), "QQ+,98s".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Ks8d2c"),
+            ..EquityOptions::default()
+        };
+        let expected = exact_equity_with_options(&ranges, &options).unwrap();
+        let result = engine.exact_equity(&ranges, &options).unwrap();
+        assert!(result.exact);
+        assert!((result.equities[0] - expected.equities[0]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_concurrent_queries() {
+        let engine = EquityEngine::new(2);
+        let ranges = HandRange::from_strings(["AsKs".to_string(), "QQ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Qh7s2s"),
+            n_threads: 4,
+            seed: Some(3),
+            ..EquityOptions::default()
+        };
+        // more work units than workers, from several threads at once
+        crossbeam::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|_| {
+                    let result = engine.approx_equity(&ranges, &options).unwrap();
+                    assert_eq!(result.stop_reason, StopReason::StdevTarget);
+                    assert!(result.equities[0] > 0.2 && result.equities[0] < 0.4);
+                });
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_cancel_on_engine() {
+        let engine = EquityEngine::new(2);
+        let ranges = HandRange::from_strings(
+            [
+                "random".to_string(),
+                "random".to_string(),
+                "random".to_string(),
+            ]
+            .to_vec(),
+        );
+        let calc = engine
+            .start_exact(&ranges, &EquityOptions::default())
+            .unwrap();
+        calc.cancel();
+        let result = calc.wait();
+        assert_eq!(result.stop_reason, StopReason::Cancelled);
+        // the workers are free for the next query
+        let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c3d4h5sQd"),
+            ..EquityOptions::default()
+        };
+        assert!(engine.exact_equity(&ranges, &options).unwrap().exact);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_rayon_pool() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let engine = EquityEngine::with_rayon_pool(Arc::new(pool));
+        let ranges = HandRange::from_strings(["AK".to_string(), "QQ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Kh7c2d"),
+            ..EquityOptions::default()
+        };
+        let expected = exact_equity_with_options(&ranges, &options).unwrap();
+        let result = engine.exact_equity(&ranges, &options).unwrap();
+        assert!((result.equities[0] - expected.equities[0]).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hand_range::Combo;
+
+/// Equity of a single combo against the other players' ranges
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ComboEquity {
+    /// Hole cards and range weight
+    pub combo: Combo,
+    /// Share of the pot won on average
+    pub equity: f64,
+    /// Fraction of weighted runouts won outright
+    pub win: f64,
+    /// Fraction of weighted runouts where the pot was split
+    pub tie: f64,
+    /// How often the player holds this combo, after card removal
+    ///
+    /// Weights of a player's combos sum to 1
+    pub weight: f64,
+}
+
+/// Why an equity calculation stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    /// Every runout was enumerated, or every thread ran out of work
+    Completed,
+    /// The first player's standard error reached `stdev_target`
+    StdevTarget,
+    /// Every player's confidence interval reached `confidence_target`
+    ConfidenceTarget,
+    /// The run used up its `time_limit`
+    TimeLimit,
+    /// The run evaluated `max_evaluations` hands
+    MaxEvaluations,
+    /// The run merged `max_batches` batches
+    MaxBatches,
+    /// No deal of hole cards could be found for the ranges
+    NoValidDeal,
+    /// The calculation was cancelled
+    Cancelled,
+}
+
+/// Results of a range vs range equity calculation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityResult {
+    /// Equity of each player
+    pub equities: Vec<f64>,
+    /// Fraction of weighted runouts each player wins outright
+    pub wins: Vec<f64>,
+    /// Fraction of weighted runouts where each player splits the pot
+    pub ties: Vec<f64>,
+    /// Fraction of weighted runouts won by each subset of players,
+    /// keyed by a bit mask of the winning players
+    ///
+    /// Subsets that never won are left out
+    pub wins_by_mask: BTreeMap<u32, f64>,
+    /// Number of hands evaluated
+    pub eval_count: u64,
+    /// Was every runout enumerated
+    pub exact: bool,
+    /// Which rule ended the calculation
+    pub stop_reason: StopReason,
+    /// Standard error of each player's equity, zero for exact results
+    pub stdev: Vec<f64>,
+    /// Equity of every combo in each player's range, if requested
+    pub combo_equities: Option<Vec<Vec<ComboEquity>>>,
+}
+
+impl EquityResult {
+    /// Confidence interval of a player's equity
+    ///
+    /// # Arguments
+    ///
+    /// * `player` Index of the player
+    /// * `z` Number of standard errors on each side, 1.96 for 95% confidence
+    ///
+    /// # Example
+    /// ```
+    /// use rust_poker::hand_range::HandRange;
+    /// use rust_poker::equity_calculator::{approx_equity_with_options, EquityOptions};
+    /// let ranges = HandRange::from_strings(["AA".to_string(), "random".to_string()].to_vec());
+    /// let result = approx_equity_with_options(&ranges, &EquityOptions::default()).unwrap();
+    /// let (low, high) = result.confidence_interval(0, 1.96);
+    /// assert!(low <= result.equities[0] && result.equities[0] <= high);
+    /// ```
+    pub fn confidence_interval(&self, player: usize, z: f64) -> (f64, f64) {
+        let equity = self.equities[player];
+        let margin = z * self.stdev[player];
+        ((equity - margin).max(0.0), (equity + margin).min(1.0))
+    }
+}
@@ -0,0 +1,523 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::simulator::{
+    batch_seed, for_each_card_subset, EquityOptions, SimulatorError, BOARD_CARDS,
+    MONTE_CARLO_BATCH_SIZE,
+};
+use super::{PendingBatches, StopReason, StoppingRules};
+use crate::constants::{CARD_COUNT, SHORT_DECK_REMOVED_CARDS};
+use crate::hand_evaluator::{evaluate, evaluate_short_deck, Hand, CARDS};
+use crate::hand_range::HandRange;
+
+const AHEAD: usize = 0;
+const TIED: usize = 1;
+const BEHIND: usize = 2;
+
+/// Strength and potential of one hand against a range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandStrength {
+    /// Chance to be ahead of the range on the current board, ties count half
+    pub hs: f64,
+    /// Mean of the squared hand strength on the river over every runout
+    pub ehs2: f64,
+    /// Chance to be ahead on the river when behind now,
+    /// ties count half on both streets
+    pub ppot: f64,
+    /// Chance to be behind on the river when ahead now,
+    /// ties count half on both streets
+    pub npot: f64,
+    /// Number of hands evaluated
+    pub eval_count: u64,
+    /// Whether every runout was enumerated
+    pub exact: bool,
+    /// Standard error of `ehs2`, 0 when exact
+    pub stdev: f64,
+    /// Why the calculation stopped
+    pub stop_reason: StopReason,
+}
+
+impl HandStrength {
+    /// Effective hand strength, `hs * (1 - npot) + (1 - hs) * ppot`
+    pub fn ehs(&self) -> f64 {
+        self.hs * (1.0 - self.npot) + (1.0 - self.hs) * self.ppot
+    }
+}
+
+/// A villain combo that can be dealt next to the hero
+struct Villain {
+    mask: u64,
+    hand: Hand,
+    weight: f64,
+    /// Hero ahead, tied or behind the combo on the current board
+    now: usize,
+}
+
+/// The hero's cards and the villain combos to evaluate every runout with
+struct Matchup {
+    board: Hand,
+    hero: Hand,
+    villains: Vec<Villain>,
+    short_deck: bool,
+}
+
+impl Matchup {
+    fn score(&self, hand: &Hand) -> u16 {
+        if self.short_deck {
+            evaluate_short_deck(hand)
+        } else {
+            evaluate(hand)
+        }
+    }
+}
+
+/// Weighted outcomes summed over runouts
+#[derive(Debug, Clone, Default)]
+struct StrengthStats {
+    /// Weight of the villain combos by state now and state on the river
+    hp: [[f64; 3]; 3],
+    /// Sum and sum of squares of the squared hand strength of each runout
+    hs2_sum: f64,
+    hs2_sq_sum: f64,
+    runouts: f64,
+    batches: f64,
+    eval_count: u64,
+}
+
+impl StrengthStats {
+    fn add_runout(&mut self, matchup: &Matchup, runout_mask: u64) {
+        let board = add_cards(matchup.board, runout_mask);
+        let hero_score = matchup.score(&(board + matchup.hero));
+        self.eval_count += 1;
+        let mut hs = [0f64; 3];
+        for v in matchup.villains.iter() {
+            if (v.mask & runout_mask) != 0 {
+                continue;
+            }
+            let score = matchup.score(&(board + v.hand));
+            self.eval_count += 1;
+            let later = match hero_score.cmp(&score) {
+                std::cmp::Ordering::Greater => AHEAD,
+                std::cmp::Ordering::Equal => TIED,
+                std::cmp::Ordering::Less => BEHIND,
+            };
+            self.hp[v.now][later] += v.weight;
+            hs[later] += v.weight;
+        }
+        let total = hs[AHEAD] + hs[TIED] + hs[BEHIND];
+        if total > 0.0 {
+            let strength = (hs[AHEAD] + hs[TIED] / 2.0) / total;
+            let hs2 = strength * strength;
+            self.hs2_sum += hs2;
+            self.hs2_sq_sum += hs2 * hs2;
+            self.runouts += 1.0;
+        }
+    }
+
+    fn merge(&mut self, other: &StrengthStats) {
+        for (a, b) in self.hp.iter_mut().zip(other.hp.iter()) {
+            for (x, y) in a.iter_mut().zip(b.iter()) {
+                *x += y;
+            }
+        }
+        self.hs2_sum += other.hs2_sum;
+        self.hs2_sq_sum += other.hs2_sq_sum;
+        self.runouts += other.runouts;
+        self.batches += other.batches;
+        self.eval_count += other.eval_count;
+    }
+
+    fn ehs2(&self) -> f64 {
+        self.hs2_sum / self.runouts.max(1.0)
+    }
+
+    /// Standard error of the mean squared hand strength
+    fn stdev(&self) -> f64 {
+        if self.runouts < 2.0 {
+            return f64::INFINITY;
+        }
+        let mean = self.ehs2();
+        let variance = (self.hs2_sq_sum / self.runouts - mean * mean).max(0.0);
+        (variance / (self.runouts - 1.0)).sqrt()
+    }
+
+    fn potentials(&self) -> (f64, f64) {
+        let totals: Vec<f64> = self.hp.iter().map(|row| row.iter().sum()).collect();
+        let ratio = |num: f64, den: f64| if den > 0.0 { num / den } else { 0.0 };
+        let ppot = ratio(
+            self.hp[BEHIND][AHEAD] + self.hp[BEHIND][TIED] / 2.0 + self.hp[TIED][AHEAD] / 2.0,
+            totals[BEHIND] + totals[TIED] / 2.0,
+        );
+        let npot = ratio(
+            self.hp[AHEAD][BEHIND] + self.hp[AHEAD][TIED] / 2.0 + self.hp[TIED][BEHIND] / 2.0,
+            totals[AHEAD] + totals[TIED] / 2.0,
+        );
+        (ppot, npot)
+    }
+}
+
+/// Calculates hand strength, squared hand strength and hand potential
+/// of a hand against a range
+///
+/// Hand strength compares the hands on the current board only.
+/// Squared hand strength and potential look at every runout to the river,
+/// so a hand that is ahead now but often outdrawn gets a high `npot`.
+/// Villain combos are weighted by their range weight after card removal.
+/// Exact enumeration is fast from the flop on,
+/// preflop it has to go through every one of the 2 million boards.
+///
+/// # Arguments
+///
+/// * `hero_mask` 64 bit mask of the hero's two hole cards
+/// * `villain_range` Range the hero plays against
+/// * `options` Board, dead cards, threads and monte carlo stopping rules,
+///   `stdev_target` applies to `ehs2`
+/// * `exact` Enumerate every runout instead of sampling
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{hand_strength, EquityOptions};
+/// let villain = HandRange::from_string("QQ,77,AQ,KQ".to_string());
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Qh7h2c3d"),
+///     ..EquityOptions::default()
+/// };
+/// let result = hand_strength(get_card_mask("AhKh"), &villain, &options, true).unwrap();
+/// // ace high is behind everything but the flush draw comes in often
+/// assert_eq!(result.hs, 0.0);
+/// assert!(result.ppot > 0.2);
+/// ```
+pub fn hand_strength(
+    hero_mask: u64,
+    villain_range: &HandRange,
+    options: &EquityOptions,
+    exact: bool,
+) -> Result<HandStrength, SimulatorError> {
+    if hero_mask.count_ones() != 2 || (hero_mask >> CARD_COUNT) != 0 {
+        return Err(SimulatorError::InvalidHoleCards);
+    }
+    if options.board_mask.count_ones() > BOARD_CARDS {
+        return Err(SimulatorError::TooManyBoardCards);
+    }
+    let mut dead_mask = options.dead_mask;
+    if options.short_deck {
+        if ((options.board_mask | hero_mask) & SHORT_DECK_REMOVED_CARDS) != 0 {
+            return Err(SimulatorError::CardNotInDeck);
+        }
+        dead_mask |= SHORT_DECK_REMOVED_CARDS;
+    }
+    if (hero_mask & (options.board_mask | dead_mask)) != 0 {
+        return Err(SimulatorError::ConflictingRanges);
+    }
+    let used_mask = hero_mask | options.board_mask | dead_mask;
+    let deck: Vec<u8> = (0..CARD_COUNT)
+        .filter(|c| (used_mask & (1u64 << c)) == 0)
+        .collect();
+    let cards_left = BOARD_CARDS - options.board_mask.count_ones();
+    if deck.len() < (cards_left + 2) as usize {
+        return Err(SimulatorError::TooManyDeadCards);
+    }
+
+    let board = Hand::from_bit_mask(options.board_mask);
+    let hero = Hand::from_hole_cards(
+        hero_mask.trailing_zeros() as u8,
+        63 - hero_mask.leading_zeros() as u8,
+    );
+    let mut matchup = Matchup {
+        board,
+        hero,
+        villains: Vec::new(),
+        short_deck: options.short_deck,
+    };
+    let hero_score = matchup.score(&(board + hero));
+    let mut hs = [0f64; 3];
+    for combo in villain_range.hands.iter() {
+        if (combo.mask() & used_mask) != 0 || combo.2 <= 0.0 {
+            continue;
+        }
+        let hand = Hand::from_hole_cards(combo.0, combo.1);
+        let score = matchup.score(&(board + hand));
+        let now = match hero_score.cmp(&score) {
+            std::cmp::Ordering::Greater => AHEAD,
+            std::cmp::Ordering::Equal => TIED,
+            std::cmp::Ordering::Less => BEHIND,
+        };
+        let weight = f64::from(combo.2);
+        hs[now] += weight;
+        matchup.villains.push(Villain {
+            mask: combo.mask(),
+            hand,
+            weight,
+            now,
+        });
+    }
+    if matchup.villains.is_empty() {
+        return Err(SimulatorError::ConflictingRanges);
+    }
+    let total: f64 = hs.iter().sum();
+
+    let (stats, stop_reason) = if exact || cards_left == 0 {
+        (
+            enumerate_runouts(&matchup, &deck, cards_left, options.n_threads),
+            StopReason::Completed,
+        )
+    } else {
+        sample_runouts(&matchup, &deck, cards_left, options)
+    };
+    let (ppot, npot) = stats.potentials();
+    let exact = exact || cards_left == 0;
+    Ok(HandStrength {
+        hs: (hs[AHEAD] + hs[TIED] / 2.0) / total,
+        ehs2: stats.ehs2(),
+        ppot,
+        npot,
+        eval_count: stats.eval_count,
+        exact,
+        stdev: if exact { 0.0 } else { stats.stdev() },
+        stop_reason,
+    })
+}
+
+/// Add the cards of a mask to a hand
+fn add_cards(mut hand: Hand, mut mask: u64) -> Hand {
+    while mask != 0 {
+        hand += CARDS[mask.trailing_zeros() as usize];
+        mask &= mask - 1;
+    }
+    hand
+}
+
+/// Evaluate every runout, split between threads by runout number
+fn enumerate_runouts(
+    matchup: &Matchup,
+    deck: &[u8],
+    cards_left: u32,
+    n_threads: u8,
+) -> StrengthStats {
+    let n_threads = u64::from(n_threads.max(1));
+    let mut stats = StrengthStats::default();
+    crossbeam::scope(|scope| {
+        let handles: Vec<_> = (0..n_threads)
+            .map(|thread_idx| {
+                scope.spawn(move |_| {
+                    let mut stats = StrengthStats::default();
+                    let mut runout_idx = 0u64;
+                    for_each_card_subset(deck, cards_left, 0, 0, &mut |runout_mask| {
+                        if runout_idx % n_threads == thread_idx {
+                            stats.add_runout(matchup, runout_mask);
+                        }
+                        runout_idx += 1;
+                    });
+                    stats
+                })
+            })
+            .collect();
+        for handle in handles {
+            stats.merge(&handle.join().unwrap());
+        }
+    })
+    .unwrap();
+    stats
+}
+
+/// Evaluate random runouts in batches until a stopping rule is met
+fn sample_runouts(
+    matchup: &Matchup,
+    deck: &[u8],
+    cards_left: u32,
+    options: &EquityOptions,
+) -> (StrengthStats, StopReason) {
+    let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+    // about as many evaluations per batch as the equity simulator
+    let batch_runouts = (MONTE_CARLO_BATCH_SIZE / (matchup.villains.len() as u64 + 1)).max(1);
+    let stopping_rules = StoppingRules::new(options);
+    let start_time = Instant::now();
+    let next_batch = AtomicU64::new(0);
+    let stopped = AtomicBool::new(false);
+    let results = Mutex::new((
+        StrengthStats::default(),
+        PendingBatches::default(),
+        StopReason::Completed,
+    ));
+
+    crossbeam::scope(|scope| {
+        for _ in 0..options.n_threads.max(1) {
+            scope.spawn(|_| {
+                while !stopped.load(Ordering::SeqCst) {
+                    let batch_idx = next_batch.fetch_add(1, Ordering::SeqCst);
+                    let mut rng = SmallRng::seed_from_u64(batch_seed(seed, batch_idx));
+                    let mut batch = StrengthStats {
+                        batches: 1.0,
+                        ..StrengthStats::default()
+                    };
+                    for _ in 0..batch_runouts {
+                        let runout_mask = deck
+                            .choose_multiple(&mut rng, cards_left as usize)
+                            .fold(0u64, |mask, c| mask | (1u64 << c));
+                        batch.add_runout(matchup, runout_mask);
+                    }
+
+                    let mut results = results.lock().unwrap();
+                    let (stats, pending, stop_reason) = &mut *results;
+                    // merge in index order so a seed gives the same result on any thread count
+                    pending.insert(batch_idx, batch);
+                    while !stopped.load(Ordering::SeqCst) {
+                        let batch = match pending.pop_next() {
+                            Some(b) => b,
+                            None => break,
+                        };
+                        stats.merge(&batch);
+                        if let Some(reason) = stopping_rules.reached(
+                            stats.batches,
+                            stats.eval_count,
+                            &[stats.stdev()],
+                        ) {
+                            *stop_reason = reason;
+                            stopped.store(true, Ordering::SeqCst);
+                        }
+                    }
+                    if !stopped.load(Ordering::SeqCst)
+                        && options
+                            .time_limit
+                            .is_some_and(|limit| start_time.elapsed() >= limit)
+                    {
+                        *stop_reason = StopReason::TimeLimit;
+                        stopped.store(true, Ordering::SeqCst);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    let (stats, _, stop_reason) = results.into_inner().unwrap();
+    (stats, stop_reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equity_calculator::exact_equity_with_options;
+    use crate::hand_range::get_card_mask;
+    use test::Bencher;
+
+    #[test]
+    fn test_river_strength() {
+        let villain = HandRange::from_string("KK,QQ".to_string());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c7d9hJsQc"),
+            ..EquityOptions::default()
+        };
+        let result = hand_strength(get_card_mask("AhAd"), &villain, &options, false).unwrap();
+        assert!(result.exact);
+        // AA beats 6 KK combos but loses to the 3 sets of queens
+        assert!((result.hs - 2.0 / 3.0).abs() < 1e-9);
+        assert!((result.ehs2 - 4.0 / 9.0).abs() < 1e-9);
+        assert_eq!(result.ppot, 0.0);
+        assert_eq!(result.npot, 0.0);
+    }
+
+    #[test]
+    fn test_negative_potential_matches_equity() {
+        // AA is always ahead of KK on this flop, so it can only lose ground
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c7d9h"),
+            ..EquityOptions::default()
+        };
+        let villain = HandRange::from_string("KK".to_string());
+        let ranges = vec![HandRange::from_string("AhAd".to_string()), villain.clone()];
+        let equity = exact_equity_with_options(&ranges, &options)
+            .unwrap()
+            .equities[0];
+        let result = hand_strength(get_card_mask("AhAd"), &villain, &options, true).unwrap();
+        assert_eq!(result.hs, 1.0);
+        assert_eq!(result.ppot, 0.0);
+        assert!((result.npot - (1.0 - equity)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_approx_matches_exact() {
+        let villain = HandRange::from_string("QQ,77,AQ,KQ,JTs,A5s".to_string());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Qh7h2c"),
+            seed: Some(4),
+            stdev_target: 0.002,
+            ..EquityOptions::default()
+        };
+        let hero = get_card_mask("AhKh");
+        let exact = hand_strength(hero, &villain, &options, true).unwrap();
+        let approx = hand_strength(hero, &villain, &options, false).unwrap();
+        assert!(!approx.exact);
+        assert_eq!(approx.stop_reason, StopReason::StdevTarget);
+        assert_eq!(approx.hs, exact.hs);
+        assert!((approx.ehs2 - exact.ehs2).abs() < 0.01);
+        assert!((approx.ppot - exact.ppot).abs() < 0.02);
+        assert!((approx.npot - exact.npot).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_seeded_runs_match() {
+        let villain = HandRange::from_string("QQ,77,AQ,KQ,JTs,A5s".to_string());
+        let hero = get_card_mask("AhKh");
+        let run = |n_threads| {
+            let options = EquityOptions {
+                board_mask: get_card_mask("Qh7h2c"),
+                seed: Some(9),
+                stdev_target: 0.0,
+                max_batches: Some(20),
+                n_threads,
+                ..EquityOptions::default()
+            };
+            hand_strength(hero, &villain, &options, false).unwrap()
+        };
+        let single = run(1);
+        let multi = run(4);
+        assert_eq!(single.stop_reason, StopReason::MaxBatches);
+        assert_eq!(multi.stop_reason, StopReason::MaxBatches);
+        assert_eq!(single.eval_count, multi.eval_count);
+        assert_eq!(single.ehs2, multi.ehs2);
+        assert_eq!(single.ppot, multi.ppot);
+        assert_eq!(single.npot, multi.npot);
+        assert_eq!(single.stdev, multi.stdev);
+    }
+
+    #[test]
+    fn test_invalid_hero() {
+        let villain = HandRange::from_string("KK".to_string());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c7d9h"),
+            ..EquityOptions::default()
+        };
+        assert!(matches!(
+            hand_strength(get_card_mask("Ah"), &villain, &options, true),
+            Err(SimulatorError::InvalidHoleCards)
+        ));
+        assert!(matches!(
+            hand_strength(get_card_mask("Ah2c"), &villain, &options, true),
+            Err(SimulatorError::ConflictingRanges)
+        ));
+        // the hero holds the villain's only combo
+        let villain = HandRange::from_string("KhKd".to_string());
+        assert!(matches!(
+            hand_strength(get_card_mask("KhQd"), &villain, &options, true),
+            Err(SimulatorError::ConflictingRanges)
+        ));
+    }
+
+    #[bench]
+    fn bench_hand_strength_flop(b: &mut Bencher) {
+        let villain = HandRange::from_string("22+,A2s+,KTo+".to_string());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Qh7h2c"),
+            n_threads: 1,
+            ..EquityOptions::default()
+        };
+        b.iter(|| hand_strength(get_card_mask("AhKh"), &villain, &options, true));
+    }
+}
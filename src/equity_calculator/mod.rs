@@ -1,5 +1,29 @@
+mod all_in;
+mod calculation;
 mod combined_range;
+mod distribution;
+mod engine;
+mod equity_result;
+mod hand_strength;
+mod multi_board;
+mod next_card;
+mod preflop_table;
+mod sampler;
 mod simulator;
 
+pub use all_in::{all_in_ev, AllInEv, Pot};
+pub use calculation::{EquityCalculation, Progress};
 pub use combined_range::CombinedRange;
-pub use simulator::{approx_equity, exact_equity};
+pub use distribution::{equity_distribution, EquityDistribution};
+pub use engine::EquityEngine;
+pub use equity_result::{ComboEquity, EquityResult, StopReason};
+pub use hand_strength::{hand_strength, HandStrength};
+pub use multi_board::{multi_board_equity, MultiBoardResult};
+pub use next_card::{next_card_equity, CardEquity, NextCardEquity};
+pub use preflop_table::{hand_classes, PreflopTable};
+pub use sampler::{sample_hands, HandSampler};
+pub use simulator::{
+    approx_equity, approx_equity_with_options, exact_equity, exact_equity_with_options,
+    BoardSampling, EquityOptions, SimulatorError,
+};
+pub(crate) use simulator::{batch_seed, PendingBatches, StoppingRules, MONTE_CARLO_BATCH_SIZE};
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+use super::simulator::{EquityOptions, MultiBoardStats, Simulator, SimulatorError};
+use super::StopReason;
+use crate::hand_range::HandRange;
+
+/// Pot shares when the pot is split between several boards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiBoardResult {
+    /// Share of the pot won by each player
+    pub equities: Vec<f64>,
+    /// How often each player wins every board outright
+    pub scoops: Vec<f64>,
+    /// Number of boards the pot is split between
+    pub n_boards: usize,
+    /// Number of hands evaluated
+    pub eval_count: u64,
+    /// Whether every deal was enumerated
+    pub exact: bool,
+    /// Standard error of each player's share, 0 when exact
+    pub stdev: Vec<f64>,
+    /// Why the calculation stopped
+    pub stop_reason: StopReason,
+}
+
+impl MultiBoardResult {
+    fn from_stats(
+        stats: &MultiBoardStats,
+        n_boards: usize,
+        exact: bool,
+        stop_reason: StopReason,
+    ) -> MultiBoardResult {
+        let total = stats.weight + 1e-9;
+        MultiBoardResult {
+            equities: stats.shares.iter().map(|s| s / total).collect(),
+            scoops: stats.scoops.iter().map(|s| s / total).collect(),
+            n_boards,
+            eval_count: stats.eval_count,
+            exact,
+            stdev: if exact {
+                vec![0f64; stats.shares.len()]
+            } else {
+                stats.stdev.clone()
+            },
+            stop_reason,
+        }
+    }
+}
+
+/// Calculates range vs range pot shares when the rest of the board is run
+/// several times
+///
+/// Every board is dealt from the same deck without replacement and
+/// each board is worth an equal part of the pot.
+/// Two boards covers both double board games and running it twice.
+/// Exact enumeration of more than one board is only practical from the turn,
+/// or from the flop with narrow ranges.
+///
+/// # Arguments
+///
+/// * `hand_ranges` Array of hand ranges
+/// * `n_boards` Number of boards to deal
+/// * `options` Board, dead cards, threads and monte carlo stopping rules
+/// * `exact` Enumerate every deal instead of sampling
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{multi_board_equity, EquityOptions};
+/// let ranges = HandRange::from_strings(["AhAd".to_string(), "KsKc".to_string()].to_vec());
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Kh7c2d5s"),
+///     ..EquityOptions::default()
+/// };
+/// let result = multi_board_equity(&ranges, 2, &options, true).unwrap();
+/// // AA needs an ace on both rivers to scoop
+/// assert!(result.scoops[0] < 0.01);
+/// assert!(result.equities[0] > 0.0);
+/// ```
+pub fn multi_board_equity(
+    hand_ranges: &[HandRange],
+    n_boards: usize,
+    options: &EquityOptions,
+    exact: bool,
+) -> Result<MultiBoardResult, SimulatorError> {
+    let sim = Simulator::prepare(hand_ranges, options, exact)?;
+    sim.check_board_count(n_boards)?;
+    let mut stats = MultiBoardStats::init(hand_ranges.len());
+    crossbeam::scope(|scope| {
+        let handles: Vec<_> = (0..options.n_threads)
+            .map(|_| {
+                scope.spawn(|_| {
+                    if exact {
+                        sim.enumerate_multi_board(n_boards)
+                    } else {
+                        sim.sim_multi_board_monte_carlo(n_boards);
+                        MultiBoardStats::init(hand_ranges.len())
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            stats.merge(&handle.join().unwrap());
+        }
+    })
+    .unwrap();
+    if !exact {
+        stats = sim.multi_board_results();
+    }
+    Ok(MultiBoardResult::from_stats(
+        &stats,
+        n_boards,
+        exact,
+        sim.stop_reason(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equity_calculator::exact_equity_with_options;
+    use crate::hand_range::get_card_mask;
+
+    fn ranges(a: &str, b: &str) -> Vec<HandRange> {
+        HandRange::from_strings([a.to_string(), b.to_string()].to_vec())
+    }
+
+    #[test]
+    fn test_river_boards_are_the_same() {
+        let ranges = ranges("AhAd", "KsKc");
+        let options = EquityOptions {
+            board_mask: get_card_mask("Kh7c2d5s3h"),
+            ..EquityOptions::default()
+        };
+        let result = multi_board_equity(&ranges, 3, &options, true).unwrap();
+        assert!((result.equities[1] - 1.0).abs() < 1e-9);
+        assert!((result.scoops[1] - 1.0).abs() < 1e-9);
+        assert_eq!(result.stop_reason, StopReason::Completed);
+    }
+
+    #[test]
+    fn test_run_it_twice_keeps_equity() {
+        // every board has the same distribution, so the pot share is the equity
+        let ranges = ranges("AK,TT", "QQ+,98s");
+        let options = EquityOptions {
+            board_mask: get_card_mask("Ks8d2c4h"),
+            ..EquityOptions::default()
+        };
+        let single = exact_equity_with_options(&ranges, &options).unwrap();
+        let result = multi_board_equity(&ranges, 2, &options, true).unwrap();
+        for i in 0..2 {
+            assert!((result.equities[i] - single.equities[i]).abs() < 1e-6);
+            assert!(result.scoops[i] <= result.equities[i] + 1e-9);
+        }
+        let approx_options = EquityOptions {
+            seed: Some(5),
+            stdev_target: 0.002,
+            ..options
+        };
+        let approx = multi_board_equity(&ranges, 2, &approx_options, false).unwrap();
+        assert!(!approx.exact);
+        assert!((approx.equities[0] - result.equities[0]).abs() < 0.02);
+        assert!((approx.scoops[0] - result.scoops[0]).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_board_count_errors() {
+        let ranges = ranges("AA", "KK");
+        let options = EquityOptions::default();
+        assert!(matches!(
+            multi_board_equity(&ranges, 0, &options, false),
+            Err(SimulatorError::NoBoards)
+        ));
+        // 10 preflop boards need 50 cards and the hands need 4 more
+        assert!(matches!(
+            multi_board_equity(&ranges, 10, &options, false),
+            Err(SimulatorError::TooManyDeadCards)
+        ));
+    }
+}
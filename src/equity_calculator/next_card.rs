@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+
+use super::simulator::{CardStats, EquityOptions, Simulator, SimulatorError};
+use crate::hand_range::HandRange;
+
+/// Equity of each player after one next board card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardEquity {
+    /// Index of the card, 4 * rank + suit
+    pub card: u8,
+    /// How often the card comes, after card removal
+    ///
+    /// Weights of all cards sum to 1
+    pub weight: f64,
+    /// Equity of each player when the card comes
+    pub equities: Vec<f64>,
+}
+
+/// Equities broken down by the next board card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NextCardEquity {
+    /// Equity of each player before the next card
+    pub equities: Vec<f64>,
+    /// Equities for every card that can come, ordered by card index
+    pub cards: Vec<CardEquity>,
+    /// Number of hands evaluated
+    pub eval_count: u64,
+}
+
+impl NextCardEquity {
+    /// Cards that improve a player's equity
+    pub fn good_cards(&self, player: usize) -> impl Iterator<Item = &CardEquity> {
+        let equity = self.equities[player];
+        self.cards
+            .iter()
+            .filter(move |c| c.equities[player] > equity)
+    }
+
+    /// Cards that worsen a player's equity
+    pub fn bad_cards(&self, player: usize) -> impl Iterator<Item = &CardEquity> {
+        let equity = self.equities[player];
+        self.cards
+            .iter()
+            .filter(move |c| c.equities[player] < equity)
+    }
+
+    /// Total weight of the good and the bad cards of a player
+    ///
+    /// Cards that leave the equity unchanged are in neither
+    pub fn good_and_bad_weight(&self, player: usize) -> (f64, f64) {
+        (
+            self.good_cards(player).map(|c| c.weight).sum(),
+            self.bad_cards(player).map(|c| c.weight).sum(),
+        )
+    }
+}
+
+/// Calculates exact range vs range equities for every possible next board card
+///
+/// # Arguments
+///
+/// * `hand_ranges` Array of hand ranges
+/// * `options` Flop or turn board, dead cards and thread count
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{next_card_equity, EquityOptions};
+/// let ranges = HandRange::from_strings(["AhKh".to_string(), "QQ".to_string()].to_vec());
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Qh7h2c"),
+///     ..EquityOptions::default()
+/// };
+/// let result = next_card_equity(&ranges, &options).unwrap();
+/// // 52 cards minus the flop and AhKh, QQ blocks no card for certain
+/// assert_eq!(result.cards.len(), 47);
+/// let (good, bad) = result.good_and_bad_weight(0);
+/// assert!(good > 0.0 && bad > 0.0);
+/// ```
+pub fn next_card_equity(
+    hand_ranges: &[HandRange],
+    options: &EquityOptions,
+) -> Result<NextCardEquity, SimulatorError> {
+    let board_cards = options.board_mask.count_ones();
+    if board_cards != 3 && board_cards != 4 {
+        return Err(SimulatorError::NotFlopOrTurn);
+    }
+    let sim = Simulator::prepare(hand_ranges, options, true)?;
+    let mut card_stats = vec![CardStats::default(); 52];
+    crossbeam::scope(|scope| {
+        let handles: Vec<_> = (0..options.n_threads)
+            .map(|_| scope.spawn(|_| sim.enumerate_next_cards()))
+            .collect();
+        for handle in handles {
+            for (total, stats) in card_stats.iter_mut().zip(handle.join().unwrap()) {
+                total.merge(&stats);
+            }
+        }
+    })
+    .unwrap();
+
+    let n_players = hand_ranges.len();
+    let total_weight: f64 = card_stats.iter().map(|s| s.weight).sum();
+    let mut equities = vec![0f64; n_players];
+    let mut cards = Vec::new();
+    let mut eval_count = 0;
+    for (card, stats) in card_stats.iter().enumerate() {
+        eval_count += stats.eval_count;
+        if stats.weight == 0.0 {
+            continue;
+        }
+        for (total, e) in equities.iter_mut().zip(stats.equity.iter()) {
+            *total += e / total_weight;
+        }
+        cards.push(CardEquity {
+            card: card as u8,
+            weight: stats.weight / total_weight,
+            equities: stats.equity.iter().map(|e| e / stats.weight).collect(),
+        });
+    }
+    Ok(NextCardEquity {
+        equities,
+        cards,
+        eval_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equity_calculator::exact_equity_with_options;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_next_card_matches_exact() {
+        let ranges = HandRange::from_strings(["AK,77".to_string(), "QQ+,JTs".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Kc9h5d"),
+            dead_mask: get_card_mask("2s"),
+            ..EquityOptions::default()
+        };
+        let result = next_card_equity(&ranges, &options).unwrap();
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        for i in 0..2 {
+            assert!((result.equities[i] - exact.equities[i]).abs() < 1e-9);
+        }
+        let weight: f64 = result.cards.iter().map(|c| c.weight).sum();
+        assert!((weight - 1.0).abs() < 1e-9);
+        let dead_card = get_card_indexes("2s")[0];
+        assert!(result.cards.iter().all(|c| c.card != dead_card));
+
+        // each card matches a turn board calculation
+        for card in result.cards.iter().filter(|c| c.card % 7 == 0) {
+            let turn_options = EquityOptions {
+                board_mask: options.board_mask | (1u64 << card.card),
+                ..options.clone()
+            };
+            let turn = exact_equity_with_options(&ranges, &turn_options).unwrap();
+            assert!((card.equities[0] - turn.equities[0]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_good_and_bad_cards() {
+        let ranges = HandRange::from_strings(["AsAd".to_string(), "KsKd".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c7h9d4s"),
+            ..EquityOptions::default()
+        };
+        let result = next_card_equity(&ranges, &options).unwrap();
+        assert_eq!(result.cards.len(), 44);
+        // only the two remaining kings save KK
+        let good: Vec<u8> = result.good_cards(1).map(|c| c.card).collect();
+        assert_eq!(good, get_card_indexes("KcKh"));
+        let (good, bad) = result.good_and_bad_weight(1);
+        assert!((good - 2.0 / 44.0).abs() < 1e-9);
+        assert!((bad - 42.0 / 44.0).abs() < 1e-9);
+        assert_eq!(result.good_and_bad_weight(0), (bad, good));
+    }
+
+    fn get_card_indexes(text: &str) -> Vec<u8> {
+        let mask = get_card_mask(text);
+        (0..52u8).filter(|i| (mask >> i) & 1 != 0).collect()
+    }
+
+    #[test]
+    fn test_next_card_needs_flop_or_turn() {
+        let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+        for board in ["", "AcKd2h3s4h"].iter() {
+            let options = EquityOptions {
+                board_mask: get_card_mask(board),
+                ..EquityOptions::default()
+            };
+            assert!(matches!(
+                next_card_equity(&ranges, &options),
+                Err(SimulatorError::NotFlopOrTurn)
+            ));
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use read_write::VecIO;
+
+use super::simulator::EquityOptions;
+use super::{EquityCalculation, EquityResult, StopReason};
+use crate::constants::RANK_TO_CHAR;
+use crate::hand_range::{Combo, HandRange};
+
+/// First word of a preflop table file
+const TABLE_MAGIC: u32 = 0x5046_5442;
+/// Version of the preflop table file layout
+const TABLE_VERSION: u32 = 1;
+/// Number of boards dealt to a heads-up preflop matchup, 48 choose 5
+const BOARD_COUNT: u32 = 1_712_304;
+
+/// Every permutation of the four suits
+const SUIT_PERMUTATIONS: [[u8; 4]; 24] = [
+    [0, 1, 2, 3],
+    [0, 1, 3, 2],
+    [0, 2, 1, 3],
+    [0, 2, 3, 1],
+    [0, 3, 1, 2],
+    [0, 3, 2, 1],
+    [1, 0, 2, 3],
+    [1, 0, 3, 2],
+    [1, 2, 0, 3],
+    [1, 2, 3, 0],
+    [1, 3, 0, 2],
+    [1, 3, 2, 0],
+    [2, 0, 1, 3],
+    [2, 0, 3, 1],
+    [2, 1, 0, 3],
+    [2, 1, 3, 0],
+    [2, 3, 0, 1],
+    [2, 3, 1, 0],
+    [3, 0, 1, 2],
+    [3, 0, 2, 1],
+    [3, 1, 0, 2],
+    [3, 1, 2, 0],
+    [3, 2, 0, 1],
+    [3, 2, 1, 0],
+];
+
+/// Pack a matchup into a key, each combo with its highest card first
+fn matchup_key(hero: (u8, u8), villain: (u8, u8)) -> u32 {
+    let (h1, h2) = (hero.0.max(hero.1), hero.0.min(hero.1));
+    let (v1, v2) = (villain.0.max(villain.1), villain.0.min(villain.1));
+    (u32::from(h1) << 18) | (u32::from(h2) << 12) | (u32::from(v1) << 6) | u32::from(v2)
+}
+
+fn unpack_key(key: u32) -> ((u8, u8), (u8, u8)) {
+    let card = |shift: u32| ((key >> shift) & 0x3f) as u8;
+    ((card(18), card(12)), (card(6), card(0)))
+}
+
+/// Smallest key of a matchup under every suit permutation and seat swap
+///
+/// Returns the key and whether the seats were swapped
+fn canonical_key(hero: (u8, u8), villain: (u8, u8)) -> (u32, bool) {
+    let mut best = (u32::MAX, false);
+    for perm in SUIT_PERMUTATIONS.iter() {
+        let map = |c: u8| (c & !3) | perm[usize::from(c & 3)];
+        let h = (map(hero.0), map(hero.1));
+        let v = (map(villain.0), map(villain.1));
+        best = best.min((matchup_key(h, v), false));
+        best = best.min((matchup_key(v, h), true));
+    }
+    best
+}
+
+/// The 169 preflop hand classes in grid order
+///
+/// Row and column ranks run from aces down to deuces, pairs are on the diagonal,
+/// suited hands above it and offsuit hands below it
+///
+/// # Example
+/// ```
+/// use rust_poker::equity_calculator::hand_classes;
+/// let classes = hand_classes();
+/// assert_eq!(classes.len(), 169);
+/// assert_eq!(&classes[0..3], ["AA", "AKs", "AQs"]);
+/// assert_eq!(classes[13], "AKo");
+/// ```
+pub fn hand_classes() -> Vec<String> {
+    let mut classes = Vec::with_capacity(169);
+    for row in (0..13).rev() {
+        for col in (0..13).rev() {
+            let (high, low) = (row.max(col), row.min(col));
+            let mut class = String::new();
+            class.push(RANK_TO_CHAR[high]);
+            class.push(RANK_TO_CHAR[low]);
+            if row < col {
+                class.push('o');
+            } else if row > col {
+                class.push('s');
+            }
+            classes.push(class);
+        }
+    }
+    classes
+}
+
+/// Exact heads-up preflop results for suit isomorphic combo matchups
+///
+/// Each matchup stores how many of the 1,712,304 boards the first hand wins
+/// and ties, so range equities built from the table are exact.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use rust_poker::hand_range::HandRange;
+/// use rust_poker::equity_calculator::{exact_equity_with_options, EquityOptions, PreflopTable};
+/// let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+/// let table = PreflopTable::generate_for(&ranges[0], &ranges[1], 4);
+/// let equity = table.class_equity("AA", "KK").unwrap();
+/// assert!(equity > 0.8 && equity < 0.85);
+/// // exact calculations answer heads-up preflop queries from the table
+/// let options = EquityOptions {
+///     preflop_table: Some(Arc::new(table)),
+///     ..EquityOptions::default()
+/// };
+/// let result = exact_equity_with_options(&ranges, &options).unwrap();
+/// assert!((result.equities[0] - equity).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreflopTable {
+    /// Sorted canonical matchup keys
+    keys: Vec<u32>,
+    /// Boards won by the first hand of each matchup
+    wins: Vec<u32>,
+    /// Boards tied in each matchup
+    ties: Vec<u32>,
+}
+
+impl PreflopTable {
+    /// Enumerate every heads-up preflop matchup
+    ///
+    /// This evaluates tens of billions of hands and takes minutes on a fast machine,
+    /// see the `gen_preflop_table` example for generating the table file once
+    pub fn generate(n_threads: u8) -> PreflopTable {
+        let random = HandRange::from_string("random".to_string());
+        PreflopTable::generate_for(&random, &random, n_threads)
+    }
+
+    /// Enumerate every matchup between two ranges
+    pub fn generate_for(hero: &HandRange, villain: &HandRange, n_threads: u8) -> PreflopTable {
+        let mut keys = Vec::new();
+        for h in &hero.hands {
+            for v in &villain.hands {
+                if (h.mask() & v.mask()) == 0 {
+                    keys.push(canonical_key((h.0, h.1), (v.0, v.1)).0);
+                }
+            }
+        }
+        keys.sort_unstable();
+        keys.dedup();
+
+        let mut results = vec![(0u32, 0u32); keys.len()];
+        let next_key = AtomicUsize::new(0);
+        crossbeam::scope(|scope| {
+            let handles: Vec<_> = (0..n_threads.max(1))
+                .map(|_| {
+                    scope.spawn(|_| {
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next_key.fetch_add(1, Ordering::Relaxed);
+                            if i >= keys.len() {
+                                break;
+                            }
+                            done.push((i, enumerate_matchup(keys[i])));
+                        }
+                        done
+                    })
+                })
+                .collect();
+            for handle in handles {
+                for (i, counts) in handle.join().unwrap() {
+                    results[i] = counts;
+                }
+            }
+        })
+        .unwrap();
+
+        PreflopTable {
+            wins: results.iter().map(|r| r.0).collect(),
+            ties: results.iter().map(|r| r.1).collect(),
+            keys,
+        }
+    }
+
+    /// Number of stored matchups
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Boards won by the hero and boards tied in a matchup
+    fn matchup(&self, hero: &Combo, villain: &Combo) -> Option<(u32, u32)> {
+        let (key, swapped) = canonical_key((hero.0, hero.1), (villain.0, villain.1));
+        let i = self.keys.binary_search(&key).ok()?;
+        let (wins, ties) = (self.wins[i], self.ties[i]);
+        if swapped {
+            Some((BOARD_COUNT - wins - ties, ties))
+        } else {
+            Some((wins, ties))
+        }
+    }
+
+    /// Exact heads-up preflop results of two ranges
+    ///
+    /// Returns `None` if a matchup is missing from the table
+    /// or the ranges have no matchup without conflicting cards
+    pub fn equity(&self, hero: &HandRange, villain: &HandRange) -> Option<EquityResult> {
+        let mut hero_wins = 0f64;
+        let mut villain_wins = 0f64;
+        let mut ties = 0f64;
+        for h in &hero.hands {
+            for v in &villain.hands {
+                if (h.mask() & v.mask()) != 0 {
+                    continue;
+                }
+                let weight = f64::from(h.2) * f64::from(v.2) / f64::from(BOARD_COUNT);
+                let (w, t) = self.matchup(h, v)?;
+                hero_wins += weight * f64::from(w);
+                ties += weight * f64::from(t);
+                villain_wins += weight * f64::from(BOARD_COUNT - w - t);
+            }
+        }
+        let total = hero_wins + villain_wins + ties;
+        if total <= 0.0 {
+            return None;
+        }
+        let (hero_wins, villain_wins, ties) =
+            (hero_wins / total, villain_wins / total, ties / total);
+        let mut wins_by_mask = BTreeMap::new();
+        for (mask, w) in [(0b01, hero_wins), (0b10, villain_wins), (0b11, ties)].iter() {
+            if *w > 0.0 {
+                wins_by_mask.insert(*mask, *w);
+            }
+        }
+        Some(EquityResult {
+            equities: vec![hero_wins + ties / 2.0, villain_wins + ties / 2.0],
+            wins: vec![hero_wins, villain_wins],
+            ties: vec![ties, ties],
+            wins_by_mask,
+            eval_count: 0,
+            exact: true,
+            stop_reason: StopReason::Completed,
+            stdev: vec![0.0, 0.0],
+            combo_equities: None,
+        })
+    }
+
+    /// Exact equity of one hand class against another, such as "AKs" and "QQ"
+    pub fn class_equity(&self, hero: &str, villain: &str) -> Option<f64> {
+        let hero = HandRange::from_hand_string(hero)?;
+        let villain = HandRange::from_hand_string(villain)?;
+        Some(self.equity(&hero, &villain)?.equities[0])
+    }
+
+    /// Equity of every hand class against every other, in `hand_classes` order
+    ///
+    /// Returns `None` unless the table holds every matchup
+    pub fn class_matrix(&self) -> Option<Vec<Vec<f64>>> {
+        let classes = hand_classes();
+        classes
+            .iter()
+            .map(|hero| {
+                classes
+                    .iter()
+                    .map(|villain| self.class_equity(hero, villain))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Write the table to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut data = Vec::with_capacity(3 + 3 * self.keys.len());
+        data.extend_from_slice(&[TABLE_MAGIC, TABLE_VERSION, self.keys.len() as u32]);
+        data.extend_from_slice(&self.keys);
+        data.extend_from_slice(&self.wins);
+        data.extend_from_slice(&self.ties);
+        File::create(path)?.write_slice_to_file::<u32>(&data)
+    }
+
+    /// Read a table written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PreflopTable> {
+        let data = File::open(path)?.read_vec_from_file::<u32>()?;
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        if data.len() < 3 || data[0] != TABLE_MAGIC {
+            return Err(invalid("not a preflop table file"));
+        }
+        if data[1] != TABLE_VERSION {
+            return Err(invalid("unsupported preflop table version"));
+        }
+        let n = data[2] as usize;
+        if data.len() != 3 + 3 * n {
+            return Err(invalid("preflop table file is truncated"));
+        }
+        Ok(PreflopTable {
+            keys: data[3..3 + n].to_vec(),
+            wins: data[3 + n..3 + 2 * n].to_vec(),
+            ties: data[3 + 2 * n..].to_vec(),
+        })
+    }
+}
+
+/// Results from the table in the options, when it can answer the query
+pub(super) fn lookup(hand_ranges: &[HandRange], options: &EquityOptions) -> Option<EquityResult> {
+    if hand_ranges.len() != 2
+        || options.board_mask != 0
+        || options.dead_mask != 0
+        || options.combo_equity
+        || options.short_deck
+    {
+        return None;
+    }
+    let table = options.preflop_table.as_ref()?;
+    table.equity(&hand_ranges[0], &hand_ranges[1])
+}
+
+/// Boards won by the first hand and boards tied in a matchup
+fn enumerate_matchup(key: u32) -> (u32, u32) {
+    let (hero, villain) = unpack_key(key);
+    let ranges = [
+        HandRange::from_combos(vec![Combo(hero.0, hero.1, 100.0)]),
+        HandRange::from_combos(vec![Combo(villain.0, villain.1, 100.0)]),
+    ];
+    let options = EquityOptions {
+        n_threads: 1,
+        ..EquityOptions::default()
+    };
+    let result = EquityCalculation::start_exact(&ranges, &options)
+        .unwrap()
+        .wait();
+    let count = |w: f64| (w * f64::from(BOARD_COUNT)).round() as u32;
+    (count(result.wins[0]), count(result.ties[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equity_calculator::exact_equity_with_options;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_canonical_key() {
+        // AsAh vs KdKc is the same matchup as AdAc vs KsKh
+        let a = canonical_key((51, 50), (45, 44));
+        let b = canonical_key((49, 48), (47, 46));
+        assert_eq!(a, b);
+        // swapping seats gives the same key
+        let (key, swapped) = canonical_key((45, 44), (51, 50));
+        assert_eq!(key, a.0);
+        assert_ne!(swapped, a.1);
+    }
+
+    #[test]
+    fn test_table_matches_enumeration() {
+        let ranges =
+            HandRange::from_strings(["QQ,AKs".to_string(), "JJ@50,AKo".to_string()].to_vec());
+        let table = PreflopTable::generate_for(&ranges[0], &ranges[1], 4);
+        let expected = exact_equity_with_options(&ranges, &EquityOptions::default()).unwrap();
+        let result = table.equity(&ranges[0], &ranges[1]).unwrap();
+        for i in 0..2 {
+            assert!((result.equities[i] - expected.equities[i]).abs() < 1e-9);
+            assert!((result.wins[i] - expected.wins[i]).abs() < 1e-9);
+            assert!((result.ties[i] - expected.ties[i]).abs() < 1e-9);
+        }
+        // the seats can be swapped
+        let swapped = table.equity(&ranges[1], &ranges[0]).unwrap();
+        assert!((swapped.equities[0] - expected.equities[1]).abs() < 1e-9);
+        // matchups outside the table are not guessed
+        let other = HandRange::from_string("22".to_string());
+        assert!(table.equity(&ranges[0], &other).is_none());
+    }
+
+    #[test]
+    fn test_lookup_uses_options_table() {
+        let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+        let mut table = PreflopTable::generate_for(&ranges[0], &ranges[1], 4);
+        // a table claiming KK always wins shows where results come from
+        for (key, wins) in table.keys.iter().zip(table.wins.iter_mut()) {
+            let (hero, _) = unpack_key(*key);
+            *wins = if hero.0 >> 2 == 12 { 0 } else { BOARD_COUNT };
+        }
+        table.ties.iter_mut().for_each(|t| *t = 0);
+        let options = EquityOptions {
+            preflop_table: Some(Arc::new(table)),
+            ..EquityOptions::default()
+        };
+        let from_table = exact_equity_with_options(&ranges, &options).unwrap();
+        assert_eq!(from_table.equities[0], 0.0);
+        // without a table, or when the table can't answer, the query is enumerated
+        let enumerated = exact_equity_with_options(&ranges, &EquityOptions::default()).unwrap();
+        assert!(enumerated.equities[0] > 0.8);
+        let with_board = EquityOptions {
+            board_mask: crate::hand_range::get_card_mask("2c"),
+            ..options
+        };
+        assert!(
+            exact_equity_with_options(&ranges, &with_board)
+                .unwrap()
+                .equities[0]
+                > 0.8
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let ranges = HandRange::from_strings(["AA".to_string(), "72o".to_string()].to_vec());
+        let table = PreflopTable::generate_for(&ranges[0], &ranges[1], 4);
+        let path = std::env::temp_dir().join(format!("preflop_table_{}.dat", std::process::id()));
+        table.save(&path).unwrap();
+        let loaded = PreflopTable::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, table);
+        assert!(!loaded.is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+use super::simulator::{deal_combos, SimulatorError, MAX_PLAYERS};
+use super::CombinedRange;
+use crate::constants::CARD_COUNT;
+use crate::hand_range::{Combo, HandRange};
+
+/// Max number of partial deals visited when checking that a deal exists
+const MAX_SEARCH_NODES: usize = 100_000;
+
+/// Deals compatible hole cards to several players from their ranges
+///
+/// Each deal is drawn in proportion to the product of the combo weights,
+/// out of all deals where no two players share a card,
+/// unless such deals are too rare to draw a full deal at once
+#[derive(Debug)]
+pub struct HandSampler {
+    /// used to reduce rejection sampling
+    combined_ranges: Vec<CombinedRange>,
+    /// weighted distribution over the combos of each combined range
+    combo_dists: Vec<WeightedIndex<f64>>,
+    /// cards that can't be dealt
+    dead_mask: u64,
+    /// number of players
+    n_players: usize,
+}
+
+impl HandSampler {
+    /// Create a sampler for a list of ranges
+    ///
+    /// Fails with `ConflictingRanges` if no deal gives every player a combo
+    /// without sharing cards
+    ///
+    /// # Arguments
+    ///
+    /// * `hand_ranges` Array of hand ranges
+    /// * `dead_mask` 64 bit mask of cards that can't be dealt
+    ///
+    /// # Example
+    /// ```
+    /// use rust_poker::hand_range::{HandRange, get_card_mask};
+    /// use rust_poker::equity_calculator::HandSampler;
+    /// let ranges = HandRange::from_strings(["AA,KK".to_string(), "AK,QQ@50".to_string()].to_vec());
+    /// let sampler = HandSampler::new(&ranges, get_card_mask("Ad")).unwrap();
+    /// let hands = sampler.sample(&mut rand::thread_rng()).unwrap();
+    /// assert_eq!(hands.len(), 2);
+    /// ```
+    pub fn new(hand_ranges: &[HandRange], dead_mask: u64) -> Result<Self, SimulatorError> {
+        if hand_ranges.is_empty() {
+            return Err(SimulatorError::TooFewPlayers);
+        }
+        if hand_ranges.len() > MAX_PLAYERS {
+            return Err(SimulatorError::TooManyPlayers);
+        }
+        if 2 * hand_ranges.len() as u32 > u32::from(CARD_COUNT) - dead_mask.count_ones() {
+            return Err(SimulatorError::TooManyDeadCards);
+        }
+        let mut hand_ranges = hand_ranges.to_owned();
+        hand_ranges.iter_mut().for_each(|h| {
+            h.remove_conflicting_combos(dead_mask);
+            h.hands.retain(|c| c.2 > 0.0);
+        });
+        let combined_ranges = CombinedRange::from_ranges(&hand_ranges);
+        let mut combo_dists = Vec::with_capacity(combined_ranges.len());
+        for cr in &combined_ranges {
+            let weights = cr.combos().iter().map(|c| {
+                c.hole_cards[0..cr.player_count()]
+                    .iter()
+                    .map(|h| f64::from(h.2))
+                    .product::<f64>()
+            });
+            let dist =
+                WeightedIndex::new(weights).map_err(|_| SimulatorError::ConflictingRanges)?;
+            combo_dists.push(dist);
+        }
+        // ranges too big to combine rarely conflict, so the search is quick
+        let mut budget = MAX_SEARCH_NODES;
+        if find_deal(&combined_ranges, dead_mask, &mut budget) == Some(false) {
+            return Err(SimulatorError::ConflictingRanges);
+        }
+        Ok(HandSampler {
+            combined_ranges,
+            combo_dists,
+            dead_mask,
+            n_players: hand_ranges.len(),
+        })
+    }
+
+    /// Deal one combo to each player
+    ///
+    /// Uses the simulator's rejection sampling, when full deals keep sharing cards
+    /// it falls back to dealing one combined range at a time.
+    /// Fails with `NoDealFound` if that fails too,
+    /// which only happens when compatible deals are very unlikely
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Result<Vec<Combo>, SimulatorError> {
+        let mut combo_indexes = [0usize; MAX_PLAYERS];
+        deal_combos(
+            &self.combined_ranges,
+            self.dead_mask,
+            &mut combo_indexes,
+            rng,
+            |i, rng| self.combo_dists[i].sample(rng),
+        )
+        .ok_or(SimulatorError::NoDealFound)?;
+        let mut hands = vec![Combo(52, 52, 0.0); self.n_players];
+        for (cr, combo_idx) in self.combined_ranges.iter().zip(combo_indexes.iter()) {
+            let combo = &cr.combos()[*combo_idx];
+            for j in 0..cr.player_count() {
+                let (c1, c2, weight) = combo.hole_cards[j];
+                hands[cr.players()[j]] = Combo(c1, c2, weight);
+            }
+        }
+        Ok(hands)
+    }
+}
+
+/// Search for one combo of each combined range such that no two share a card
+///
+/// Returns whether a deal exists, or `None` if the search ran out of budget
+fn find_deal(
+    combined_ranges: &[CombinedRange],
+    used_cards_mask: u64,
+    budget: &mut usize,
+) -> Option<bool> {
+    let (cr, rest) = match combined_ranges.split_first() {
+        Some(split) => split,
+        None => return Some(true),
+    };
+    for combo in cr.combos() {
+        if (combo.mask & used_cards_mask) != 0 {
+            continue;
+        }
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
+        if find_deal(rest, used_cards_mask | combo.mask, budget)? {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
+/// Deal one combo to each player from their ranges
+///
+/// Fails if the ranges conflict with each other or the dead cards
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::HandRange;
+/// use rust_poker::equity_calculator::sample_hands;
+/// let ranges = HandRange::from_strings(["JJ+".to_string(), "random".to_string()].to_vec());
+/// let hands = sample_hands(&ranges, &mut rand::thread_rng(), 0).unwrap();
+/// ```
+pub fn sample_hands<R: Rng>(
+    hand_ranges: &[HandRange],
+    rng: &mut R,
+    dead_mask: u64,
+) -> Result<Vec<Combo>, SimulatorError> {
+    HandSampler::new(hand_ranges, dead_mask)?.sample(rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sample_hands() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let ranges = HandRange::from_strings(
+            [
+                "AA,KK".to_string(),
+                "AA,KK,QQ".to_string(),
+                "random".to_string(),
+            ]
+            .to_vec(),
+        );
+        let dead_mask = get_card_mask("Qs");
+        let sampler = HandSampler::new(&ranges, dead_mask).unwrap();
+        for _ in 0..1000 {
+            let hands = sampler.sample(&mut rng).unwrap();
+            assert_eq!(hands.len(), 3);
+            let mut used = dead_mask;
+            for h in &hands {
+                assert_eq!(used & h.mask(), 0);
+                used |= h.mask();
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_unbiased() {
+        // AA vs {AA, KK}: the second player can only hold AA when
+        // the first player leaves two aces, so deals are
+        // 6 * 1 (AA vs AA) against 6 * 6 (AA vs KK)
+        let mut rng = SmallRng::seed_from_u64(3);
+        let ranges = HandRange::from_strings(["AA".to_string(), "AA,KK".to_string()].to_vec());
+        let sampler = HandSampler::new(&ranges, 0).unwrap();
+        let mut aces = 0;
+        for _ in 0..14000 {
+            let hands = sampler.sample(&mut rng).unwrap();
+            if hands[1].0 >> 2 == 12 {
+                aces += 1;
+            }
+        }
+        assert!(aces > 1700 && aces < 2300);
+    }
+
+    #[test]
+    fn test_sample_conflicting() {
+        let ranges = HandRange::from_strings(["AsAh".to_string(), "AsKs".to_string()].to_vec());
+        assert!(HandSampler::new(&ranges, 0).is_err());
+        let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+        assert!(matches!(
+            sample_hands(&ranges, &mut rand::thread_rng(), get_card_mask("AsAhAd")),
+            Err(SimulatorError::ConflictingRanges)
+        ));
+    }
+
+    #[test]
+    fn test_sample_infeasible_deck() {
+        // seven live cards: any three players fit, a fourth never does
+        let dead_mask = !get_card_mask("AsAhAdAcKsKhKd") & ((1u64 << 52) - 1);
+        let ranges = vec![HandRange::from_string("random".to_string()); 4];
+        // search the ranges one player at a time, as if they were too big to combine
+        let separate_ranges: Vec<CombinedRange> = ranges
+            .iter()
+            .flat_map(|r| {
+                let mut r = r.clone();
+                r.remove_conflicting_combos(dead_mask);
+                CombinedRange::from_ranges(&[r])
+            })
+            .collect();
+        let mut budget = MAX_SEARCH_NODES;
+        assert_eq!(
+            find_deal(&separate_ranges, dead_mask, &mut budget),
+            Some(false)
+        );
+        let mut budget = MAX_SEARCH_NODES;
+        assert_eq!(
+            find_deal(&separate_ranges[..3], dead_mask, &mut budget),
+            Some(true)
+        );
+        let mut budget = 2;
+        assert_eq!(find_deal(&separate_ranges, dead_mask, &mut budget), None);
+        assert!(matches!(
+            HandSampler::new(&ranges, dead_mask),
+            Err(SimulatorError::TooManyDeadCards)
+        ));
+        let sampler = HandSampler::new(&ranges[..3], dead_mask).unwrap();
+        assert!(sampler.sample(&mut rand::thread_rng()).is_ok());
+    }
+}
@@ -1,28 +1,66 @@
 use crossbeam::atomic::AtomicCell;
+use crossbeam::channel::{self, Receiver, Sender};
 
 use fastdivide::DividerU64;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use thiserror::Error;
 
 use std::error::Error;
 use std::result::Result;
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-use rand::distributions::{Distribution, Uniform};
+use rand::distributions::{Distribution, Uniform, WeightedIndex};
 use rand::rngs::SmallRng;
 use rand::{thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-use super::CombinedRange;
-use crate::constants::{CARD_COUNT, RANK_MASK, SUIT_COUNT, SUIT_MASK};
-use crate::hand_evaluator::{evaluate, evaluate_without_flush, Hand, CARDS};
-use crate::hand_range::HandRange;
+use super::preflop_table::{self, PreflopTable};
+use super::{CombinedRange, ComboEquity, EquityCalculation, EquityResult, Progress, StopReason};
+use crate::constants::{CARD_COUNT, RANK_MASK, SHORT_DECK_REMOVED_CARDS, SUIT_COUNT, SUIT_MASK};
+use crate::hand_evaluator::{evaluate, evaluate_short_deck, evaluate_without_flush, Hand, CARDS};
+use crate::hand_range::{Combo, HandRange};
 
 // use super::combined_range::CombinedRange;
 
 const MIN_PLAYERS: usize = 2;
-const MAX_PLAYERS: usize = 6;
-const BOARD_CARDS: u32 = 5;
+/// Max player count
+pub(crate) const MAX_PLAYERS: usize = 23;
+/// Max player count for the preflop lookup table, preflop ids overflow beyond this
+const LOOKUP_MAX_PLAYERS: usize = 6;
+/// Max player count for which results are stored for every possible winner mask
+const DENSE_MASK_PLAYERS: usize = 10;
+/// Number of evaluations in each monte carlo batch
+pub(crate) const MONTE_CARLO_BATCH_SIZE: u64 = 0x1000;
+/// Number of monte carlo batches needed before the stdev estimate is trusted
+pub(super) const MIN_MONTE_CARLO_BATCHES: f64 = 8.0;
+/// Number of standard errors on each side of a 95% confidence interval
+pub(super) const CONFIDENCE_Z: f64 = 1.96;
+pub(super) const BOARD_CARDS: u32 = 5;
+/// Number of distinct hole card combos
+const COMBO_COUNT: usize = 1326;
+/// Mask of all 52 cards
+const FULL_DECK_MASK: u64 = (1u64 << 52) - 1;
+/// Number of full deals drawn before dealing one combined range at a time
+const MAX_DEAL_ATTEMPTS: usize = 1000;
+/// Number of one range at a time deals before giving up
+const MAX_PARTIAL_DEAL_ATTEMPTS: usize = 100;
+/// Give up on a batch after this many deals without filling it
+const MAX_SAMPLES_PER_BATCH: u64 = 64 * MONTE_CARLO_BATCH_SIZE;
+/// Odd step close to sqrt(2) - 1 of the batch size, scatters board strata
+const STRATUM_STRIDE: u64 = 1697;
+/// Step of the sequence spreading the first range's combos, the golden ratio
+const COMBO_STEP: f64 = 0.618_033_988_749_894_9;
+/// Steps of the additive low discrepancy sequence for each board card,
+/// powers of one over the root of x^6 = x + 1
+const QUASI_RANDOM_ALPHA: [f64; BOARD_CARDS as usize] = [
+    0.881_271_461_633_569_6,
+    0.776_639_389_089_768_3,
+    0.684_430_129_585_342_7,
+    0.603_168_740_685_728_3,
+    0.531_555_397_715_791_4,
+];
 
 #[derive(Debug, Error)]
 pub enum SimulatorError {
@@ -34,6 +72,104 @@ pub enum SimulatorError {
     TooManyBoardCards,
     #[error("conflicting ranges")]
     ConflictingRanges,
+    #[error("not enough cards left in the deck")]
+    TooManyDeadCards,
+    #[error("too many combos to enumerate")]
+    TooManyCombos,
+    #[error("board must be a flop or turn")]
+    NotFlopOrTurn,
+    #[error("board card is not in the deck")]
+    CardNotInDeck,
+    #[error("at least one board is needed")]
+    NoBoards,
+    #[error("need a non-negative amount for every player")]
+    InvalidAmounts,
+    #[error("hole cards must be two cards")]
+    InvalidHoleCards,
+    #[error("no compatible deal found within the attempt limit")]
+    NoDealFound,
+    #[error("no player with that index")]
+    InvalidPlayer,
+}
+
+/// How monte carlo simulation picks the cards left to deal on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoardSampling {
+    /// Independent random cards
+    Random,
+    /// Spread the next board card evenly over each batch
+    Stratified,
+    /// Evaluate every random board together with its mirror image,
+    /// where low cards become high cards
+    Antithetic,
+    /// Low discrepancy sequence with a random shift for each batch
+    QuasiRandom,
+}
+
+/// Options for an equity calculation
+#[derive(Debug, Clone)]
+pub struct EquityOptions {
+    /// 64 bit mask of public cards
+    pub board_mask: u64,
+    /// 64 bit mask of cards out of play, such as folded or exposed cards
+    pub dead_mask: u64,
+    /// Number of threads to use in simulation
+    pub n_threads: u8,
+    /// Target std deviation of the first player's equity for monte carlo simulation
+    ///
+    /// Zero disables this target
+    pub stdev_target: f64,
+    /// Target half width of the 95% confidence interval of every player's equity
+    /// for monte carlo simulation
+    pub confidence_target: Option<f64>,
+    /// Wall clock budget for monte carlo simulation
+    pub time_limit: Option<Duration>,
+    /// Max number of hands evaluated in monte carlo simulation
+    ///
+    /// Checked after each batch of 4096 evaluations
+    pub max_evaluations: Option<u64>,
+    /// Max number of batches of 4096 evaluations in monte carlo simulation
+    pub max_batches: Option<u64>,
+    /// Calculate the equity of every combo in each range
+    pub combo_equity: bool,
+    /// Seed for monte carlo simulation
+    ///
+    /// Runs with the same seed give the same results for any thread count.
+    /// A random seed is used if `None`
+    pub seed: Option<u64>,
+    /// Play with a 36 card deck of sixes to aces, using short deck hand ranks
+    pub short_deck: bool,
+    /// How monte carlo simulation deals the rest of the board
+    pub board_sampling: BoardSampling,
+    /// Deal the first range's combos in proportion to their weight, spread evenly
+    /// over each batch, instead of a random walk weighted after the fact
+    ///
+    /// Helps most when weights vary a lot
+    pub stratify_combos: bool,
+    /// Exact heads-up preflop results that exact calculations are answered from
+    /// when the table holds every matchup of the query
+    pub preflop_table: Option<Arc<PreflopTable>>,
+}
+
+impl Default for EquityOptions {
+    fn default() -> Self {
+        EquityOptions {
+            board_mask: 0,
+            dead_mask: 0,
+            n_threads: 4,
+            stdev_target: 0.001,
+            confidence_target: None,
+            time_limit: None,
+            max_evaluations: None,
+            max_batches: None,
+            combo_equity: false,
+            seed: None,
+            short_deck: false,
+            board_sampling: BoardSampling::Random,
+            stratify_combos: false,
+            preflop_table: None,
+        }
+    }
 }
 
 /// Calculates exact range vs range equities
@@ -59,46 +195,47 @@ pub fn exact_equity(
     board_mask: u64,
     n_threads: u8,
 ) -> Result<Vec<f64>, SimulatorError> {
-    if hand_ranges.len() < MIN_PLAYERS {
-        return Err(SimulatorError::TooFewPlayers);
-    }
-    if hand_ranges.len() > MAX_PLAYERS {
-        return Err(SimulatorError::TooManyPlayers);
-    }
-    if board_mask.count_ones() > BOARD_CARDS {
-        return Err(SimulatorError::TooManyBoardCards);
-    }
+    let options = EquityOptions {
+        board_mask,
+        n_threads,
+        ..EquityOptions::default()
+    };
+    Ok(exact_equity_with_options(hand_ranges, &options)?.equities)
+}
 
-    let mut hand_ranges = hand_ranges.to_owned();
-    hand_ranges
-        .iter_mut()
-        .for_each(|h| h.remove_conflicting_combos(board_mask));
-    let combined_ranges = CombinedRange::from_ranges(&hand_ranges);
-    for cr in &combined_ranges {
-        if cr.size() == 0 {
-            return Err(SimulatorError::ConflictingRanges);
-        }
+/// Calculates exact range vs range equities with extra options
+///
+/// Heads-up preflop queries are answered from `options.preflop_table`
+/// when it holds every matchup
+///
+/// # Arguments
+///
+/// * `hand_ranges` Array of hand ranges
+/// * `options` Board, thread count and which results to calculate
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::{HandRange, get_card_mask};
+/// use rust_poker::equity_calculator::{exact_equity_with_options, EquityOptions};
+/// let ranges = HandRange::from_strings(["QQ+".to_string(), "AK,JJ".to_string()].to_vec());
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Kh7c2d"),
+///     combo_equity: true,
+///     ..EquityOptions::default()
+/// };
+/// let result = exact_equity_with_options(&ranges, &options).unwrap();
+/// let combo_equities = result.combo_equities.unwrap();
+/// // AA, QQ and the three remaining KK combos
+/// assert_eq!(combo_equities[0].len(), 15);
+/// ```
+pub fn exact_equity_with_options(
+    hand_ranges: &[HandRange],
+    options: &EquityOptions,
+) -> Result<EquityResult, SimulatorError> {
+    if let Some(result) = preflop_table::lookup(hand_ranges, options) {
+        return Ok(result);
     }
-    let sim = Arc::new(Simulator::new(
-        hand_ranges,
-        combined_ranges,
-        board_mask,
-        true,
-        0.0,
-    ));
-    // spawn threads
-    crossbeam::scope(|scope| {
-        for _ in 0..n_threads {
-            let sim = Arc::clone(&sim);
-            scope.spawn(move |_| {
-                sim.enumerate_all();
-            });
-        }
-    })
-    .unwrap();
-    // get results and calculate equity
-    let results = sim.results.read().unwrap();
-    Ok(results.get_equity())
+    Ok(EquityCalculation::start_exact(hand_ranges, options)?.wait())
 }
 
 /// Runs a monte carlo simulation to calculate range vs range equity
@@ -126,49 +263,129 @@ pub fn approx_equity(
     n_threads: u8,
     stdev_target: f64,
 ) -> Result<Vec<f64>, SimulatorError> {
-    if hand_ranges.len() < MIN_PLAYERS {
-        return Err(SimulatorError::TooFewPlayers);
-    }
-    if hand_ranges.len() > MAX_PLAYERS {
-        return Err(SimulatorError::TooManyPlayers);
-    }
-    if board_mask.count_ones() > BOARD_CARDS {
-        return Err(SimulatorError::TooManyBoardCards);
+    let options = EquityOptions {
+        board_mask,
+        n_threads,
+        stdev_target,
+        ..EquityOptions::default()
+    };
+    Ok(approx_equity_with_options(hand_ranges, &options)?.equities)
+}
+
+/// Runs a monte carlo simulation to calculate range vs range equity with extra options
+///
+/// # Arguments
+///
+/// * `hand_ranges` Array of hand ranges
+/// * `options` Board, thread count, stdev target and which results to calculate
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_range::HandRange;
+/// use rust_poker::equity_calculator::{approx_equity_with_options, EquityOptions};
+/// let ranges = HandRange::from_strings(["AA,KK".to_string(), "random".to_string()].to_vec());
+/// let options = EquityOptions {
+///     combo_equity: true,
+///     ..EquityOptions::default()
+/// };
+/// let result = approx_equity_with_options(&ranges, &options).unwrap();
+/// assert_eq!(result.combo_equities.unwrap()[0].len(), 12);
+/// ```
+pub fn approx_equity_with_options(
+    hand_ranges: &[HandRange],
+    options: &EquityOptions,
+) -> Result<EquityResult, SimulatorError> {
+    Ok(EquityCalculation::start_approx(hand_ranges, options)?.wait())
+}
+
+/// Seed of the random stream used by a monte carlo batch
+pub(crate) fn batch_seed(seed: u64, batch_idx: u64) -> u64 {
+    seed ^ batch_idx.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// Targets and budgets that end a monte carlo simulation
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StoppingRules {
+    /// target stdev of the first player's equity
+    stdev_target: f64,
+    /// target confidence interval half width for every player
+    confidence_target: Option<f64>,
+    /// max evaluations
+    max_evaluations: Option<u64>,
+    /// max batches
+    max_batches: Option<u64>,
+}
+
+impl StoppingRules {
+    pub(crate) fn new(options: &EquityOptions) -> Self {
+        StoppingRules {
+            stdev_target: options.stdev_target,
+            confidence_target: options.confidence_target,
+            max_evaluations: options.max_evaluations,
+            max_batches: options.max_batches,
+        }
     }
 
-    let mut rng = thread_rng();
-    let mut hand_ranges = hand_ranges.to_owned();
-    hand_ranges
-        .iter_mut()
-        .for_each(|h| h.remove_conflicting_combos(board_mask));
-    let mut combined_ranges = CombinedRange::from_ranges(&hand_ranges);
-    for cr in &mut combined_ranges {
-        if cr.size() == 0 {
-            return Err(SimulatorError::ConflictingRanges);
+    /// The first rule met after merging `batch_count` batches, if any
+    ///
+    /// `stdev` holds the standard error of each player's equity
+    pub(crate) fn reached(
+        &self,
+        batch_count: f64,
+        eval_count: u64,
+        stdev: &[f64],
+    ) -> Option<StopReason> {
+        let trusted = batch_count >= MIN_MONTE_CARLO_BATCHES;
+        let worst_stdev = stdev.iter().cloned().fold(0.0, f64::max);
+        if trusted && stdev[0] < self.stdev_target {
+            Some(StopReason::StdevTarget)
+        } else if trusted
+            && self
+                .confidence_target
+                .is_some_and(|target| CONFIDENCE_Z * worst_stdev < target)
+        {
+            Some(StopReason::ConfidenceTarget)
+        } else if self.max_evaluations.is_some_and(|max| eval_count >= max) {
+            Some(StopReason::MaxEvaluations)
+        } else if self
+            .max_batches
+            .is_some_and(|max| batch_count >= max as f64)
+        {
+            Some(StopReason::MaxBatches)
+        } else {
+            None
         }
-        cr.shuffle(&mut rng);
     }
-    let sim = Arc::new(Simulator::new(
-        hand_ranges,
-        combined_ranges,
-        board_mask,
-        false,
-        stdev_target,
-    ));
-    // spawn threads
-    crossbeam::scope(|scope| {
-        for _ in 0..n_threads {
-            let sim = Arc::clone(&sim);
-            let mut rng = SmallRng::from_rng(&mut rng).unwrap();
-            scope.spawn(move |_| {
-                sim.sim_random_walk_monte_carlo(&mut rng);
-            });
+}
+
+/// Index of a two card combo in 0..COMBO_COUNT
+fn combo_index(c1: u8, c2: u8) -> usize {
+    let (hi, lo) = if c1 > c2 { (c1, c2) } else { (c2, c1) };
+    ((usize::from(hi) * usize::from(hi - 1)) >> 1) + usize::from(lo)
+}
+
+/// Add the result of one weighted runout to the stats of each player's combo
+///
+/// `winner_mask` is indexed by player
+fn record_combo_stats(
+    combo_stats: &mut [Vec<ComboStats>],
+    hole_cards: &[(u8, u8, f32)],
+    winner_mask: usize,
+    weight: f64,
+) {
+    let winner_count = winner_mask.count_ones();
+    for (i, player_stats) in combo_stats.iter_mut().enumerate() {
+        let stats = &mut player_stats[combo_index(hole_cards[i].0, hole_cards[i].1)];
+        stats.total += weight;
+        if (winner_mask & (1 << i)) != 0 {
+            if winner_count == 1 {
+                stats.wins += weight;
+            } else {
+                stats.ties += weight;
+                stats.tie_equity += weight / f64::from(winner_count);
+            }
         }
-    })
-    .unwrap();
-    // get results and calculate equity
-    let results = sim.results.read().unwrap();
-    Ok(results.get_equity())
+    }
 }
 
 fn calculate_preflop_id(player_hands: &[HandWithIndex], n_players: usize) -> u64 {
@@ -185,56 +402,142 @@ fn calculate_preflop_id(player_hands: &[HandWithIndex], n_players: usize) -> u64
 }
 
 /// stores total results of the simulation
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SimulationResults {
-    wins: Vec<u64>,
+    wins: Vec<f64>,
     ties: Vec<f64>,
-    wins_by_mask: Vec<u64>,
+    wins_by_mask: WinsByMask,
     eval_count: u64,
-    batch_sum: f64,
-    batch_sum2: f64,
+    /// sum of each player's batch equities
+    batch_sum: Vec<f64>,
+    /// sum of each player's squared batch equities
+    batch_sum2: Vec<f64>,
     batch_count: f64,
-    stdev: f64,
+    /// standard error of each player's equity
+    stdev: Vec<f64>,
+    /// results of each combo, indexed by player and combo index
+    combo_stats: Vec<Vec<ComboStats>>,
 }
 
 impl SimulationResults {
-    fn init(n_players: usize) -> SimulationResults {
+    fn init(n_players: usize, combo_equity: bool) -> SimulationResults {
         SimulationResults {
-            wins: vec![0u64; n_players],
+            wins: vec![0f64; n_players],
             ties: vec![0f64; n_players],
-            wins_by_mask: vec![0u64; 1 << n_players],
+            wins_by_mask: WinsByMask::new(n_players),
             eval_count: 0,
             batch_count: 0f64,
-            batch_sum: 0f64,
-            batch_sum2: 0f64,
-            stdev: 0f64,
+            batch_sum: vec![0f64; n_players],
+            batch_sum2: vec![0f64; n_players],
+            stdev: vec![0f64; n_players],
+            combo_stats: if combo_equity {
+                vec![vec![ComboStats::default(); COMBO_COUNT]; n_players]
+            } else {
+                Vec::new()
+            },
         }
     }
     fn get_equity(&self) -> Vec<f64> {
         let mut equity = vec![0f64; self.wins.len()];
         let mut equity_sum = 0f64;
         for i in 0..self.wins.len() {
-            equity[i] += self.wins[i] as f64;
+            equity[i] += self.wins[i];
             equity[i] += self.ties[i];
             equity_sum += equity[i];
         }
-        for e in &mut equity {
-            *e /= equity_sum;
+        if equity_sum > 0.0 {
+            for e in &mut equity {
+                *e /= equity_sum;
+            }
         }
         equity
     }
+    fn get_wins_and_ties(&self) -> (Vec<f64>, Vec<f64>, BTreeMap<u32, f64>) {
+        let n_players = self.wins.len();
+        let mut total = 0f64;
+        self.wins_by_mask.for_each(|_, w| total += w);
+        let total = total.max(1e-300);
+        let mut wins_by_mask = BTreeMap::new();
+        let mut wins = vec![0f64; n_players];
+        let mut ties = vec![0f64; n_players];
+        self.wins_by_mask.for_each(|mask, w| {
+            let w = w / total;
+            wins_by_mask.insert(mask, w);
+            for i in 0..n_players {
+                if (mask & (1 << i)) == 0 {
+                    continue;
+                }
+                if mask.count_ones() == 1 {
+                    wins[i] += w;
+                } else {
+                    ties[i] += w;
+                }
+            }
+        });
+        (wins, ties, wins_by_mask)
+    }
+    fn get_combo_equities(&self, hand_ranges: &[HandRange]) -> Option<Vec<Vec<ComboEquity>>> {
+        if self.combo_stats.is_empty() {
+            return None;
+        }
+        let mut combo_equities = Vec::with_capacity(hand_ranges.len());
+        for (player_stats, range) in self.combo_stats.iter().zip(hand_ranges) {
+            let player_total: f64 = player_stats.iter().map(|s| s.total).sum();
+            let equities = range
+                .hands
+                .iter()
+                .filter_map(|c| {
+                    let stats = &player_stats[combo_index(c.0, c.1)];
+                    if stats.total <= 0.0 {
+                        return None;
+                    }
+                    Some(ComboEquity {
+                        combo: Combo(c.0, c.1, c.2),
+                        equity: (stats.wins + stats.tie_equity) / stats.total,
+                        win: stats.wins / stats.total,
+                        tie: stats.ties / stats.total,
+                        weight: stats.total / player_total,
+                    })
+                })
+                .collect();
+            combo_equities.push(equities);
+        }
+        Some(combo_equities)
+    }
+}
+
+/// weighted results of a single combo
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct ComboStats {
+    /// weighted runouts won outright
+    wins: f64,
+    /// weighted runouts where the pot was split
+    ties: f64,
+    /// share of split pots won
+    tie_equity: f64,
+    /// all weighted runouts
+    total: f64,
+}
+
+impl ComboStats {
+    fn add(&mut self, other: &ComboStats) {
+        self.wins += other.wins;
+        self.ties += other.ties;
+        self.tie_equity += other.tie_equity;
+        self.total += other.total;
+    }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy)]
 struct HandWithIndex {
-    pub cards: (u8, u8, u8),
+    pub cards: (u8, u8, f32),
     pub player_idx: usize,
 }
 
 impl Default for HandWithIndex {
     fn default() -> Self {
         HandWithIndex {
-            cards: (52, 52, 100),
+            cards: (52, 52, 100.0),
             player_idx: 0,
         }
     }
@@ -255,16 +558,68 @@ impl Ord for HandWithIndex {
     }
 }
 
+impl PartialEq for HandWithIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.cards.0 == other.cards.0 && self.cards.1 == other.cards.1
+    }
+}
+
+impl Eq for HandWithIndex {}
+
 impl PartialOrd for HandWithIndex {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// weighted results for each set of winning players
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum WinsByMask {
+    /// indexed by winner mask, used for small player counts
+    Dense(Vec<f64>),
+    /// only the winner masks that occurred
+    Sparse(HashMap<u32, f64>),
+}
+
+impl WinsByMask {
+    fn new(n_players: usize) -> Self {
+        if n_players <= DENSE_MASK_PLAYERS {
+            WinsByMask::Dense(vec![0f64; 1 << n_players])
+        } else {
+            WinsByMask::Sparse(HashMap::new())
+        }
+    }
+
+    fn add(&mut self, mask: u32, weight: f64) {
+        match self {
+            WinsByMask::Dense(wins) => wins[mask as usize] += weight,
+            WinsByMask::Sparse(wins) => *wins.entry(mask).or_insert(0f64) += weight,
+        }
+    }
+
+    /// Call `f` with every winner mask that has results
+    fn for_each<F: FnMut(u32, f64)>(&self, mut f: F) {
+        match self {
+            WinsByMask::Dense(wins) => {
+                for (mask, w) in wins.iter().enumerate() {
+                    if *w != 0.0 {
+                        f(mask as u32, *w);
+                    }
+                }
+            }
+            WinsByMask::Sparse(wins) => {
+                for (mask, w) in wins {
+                    f(*mask, *w);
+                }
+            }
+        }
+    }
+}
+
 /// structure to store results of a single thread
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 struct SimulationResultsBatch {
-    wins_by_mask: [u64; 1 << MAX_PLAYERS],
+    wins_by_mask: WinsByMask,
     player_ids: [usize; MAX_PLAYERS],
     eval_count: u64,
 }
@@ -276,76 +631,556 @@ impl SimulationResultsBatch {
             player_ids[i] = i;
         }
         SimulationResultsBatch {
-            wins_by_mask: [0u64; 1 << MAX_PLAYERS],
+            wins_by_mask: WinsByMask::new(n_players),
             player_ids,
             eval_count: 0,
         }
     }
+
+    fn merge(&mut self, other: &SimulationResultsBatch) {
+        let wins_by_mask = &mut self.wins_by_mask;
+        other
+            .wins_by_mask
+            .for_each(|mask, w| wins_by_mask.add(mask, w));
+        self.eval_count += other.eval_count;
+    }
+}
+
+/// weighted results of every runout after one next board card
+#[derive(Debug, Clone, Default)]
+pub(super) struct CardStats {
+    /// total weight of the runouts
+    pub(super) weight: f64,
+    /// weighted share of the pot won by each player
+    pub(super) equity: Vec<f64>,
+    /// number of hands evaluated
+    pub(super) eval_count: u64,
+}
+
+impl CardStats {
+    fn from_batch(batch: &SimulationResultsBatch, n_players: usize) -> CardStats {
+        let mut stats = CardStats {
+            equity: vec![0f64; n_players],
+            eval_count: batch.eval_count,
+            ..CardStats::default()
+        };
+        batch.wins_by_mask.for_each(|mask, w| {
+            let winner_count = mask.count_ones();
+            stats.weight += w;
+            for (i, equity) in stats.equity.iter_mut().enumerate() {
+                if (mask & (1 << batch.player_ids[i])) != 0 {
+                    *equity += w / f64::from(winner_count);
+                }
+            }
+        });
+        stats
+    }
+
+    pub(super) fn merge(&mut self, other: &CardStats) {
+        self.weight += other.weight;
+        if self.equity.is_empty() {
+            self.equity = vec![0f64; other.equity.len()];
+        }
+        for (e, o) in self.equity.iter_mut().zip(other.equity.iter()) {
+            *e += o;
+        }
+        self.eval_count += other.eval_count;
+    }
+}
+
+/// pot shares won when the pot is split between several boards
+#[derive(Debug, Clone, Default)]
+pub(super) struct MultiBoardStats {
+    /// weighted share of the pot won by each player
+    pub(super) shares: Vec<f64>,
+    /// weighted deals where each player won every board outright
+    pub(super) scoops: Vec<f64>,
+    /// total weight of the deals
+    pub(super) weight: f64,
+    /// number of hands evaluated
+    pub(super) eval_count: u64,
+    /// sum of each player's batch share for monte carlo
+    batch_sum: Vec<f64>,
+    /// sum of each player's squared batch share for monte carlo
+    batch_sum2: Vec<f64>,
+    batch_count: f64,
+    /// standard error of each player's share
+    pub(super) stdev: Vec<f64>,
+}
+
+impl MultiBoardStats {
+    pub(super) fn init(n_players: usize) -> MultiBoardStats {
+        MultiBoardStats {
+            shares: vec![0f64; n_players],
+            scoops: vec![0f64; n_players],
+            batch_sum: vec![0f64; n_players],
+            batch_sum2: vec![0f64; n_players],
+            stdev: vec![0f64; n_players],
+            ..MultiBoardStats::default()
+        }
+    }
+
+    /// Add a deal given the winner mask of each board
+    fn add(&mut self, winner_masks: &[u32], weight: f64) {
+        let share = weight / winner_masks.len() as f64;
+        for mask in winner_masks {
+            let winner_count = f64::from(mask.count_ones());
+            for (i, s) in self.shares.iter_mut().enumerate() {
+                if (mask & (1 << i)) != 0 {
+                    *s += share / winner_count;
+                }
+            }
+        }
+        let first = winner_masks[0];
+        if first.count_ones() == 1 && winner_masks.iter().all(|m| *m == first) {
+            self.scoops[first.trailing_zeros() as usize] += weight;
+        }
+        self.weight += weight;
+    }
+
+    pub(super) fn merge(&mut self, other: &MultiBoardStats) {
+        for (s, o) in self.shares.iter_mut().zip(other.shares.iter()) {
+            *s += o;
+        }
+        for (s, o) in self.scoops.iter_mut().zip(other.scoops.iter()) {
+            *s += o;
+        }
+        self.weight += other.weight;
+        self.eval_count += other.eval_count;
+    }
+
+    /// Merge a monte carlo batch and update the standard errors
+    fn merge_batch(&mut self, batch: &MultiBoardStats) {
+        self.merge(batch);
+        self.batch_count += 1.0;
+        for i in 0..self.shares.len() {
+            let share = batch.shares[i] / (batch.weight + 1e-9);
+            self.batch_sum[i] += share;
+            self.batch_sum2[i] += share * share;
+            self.stdev[i] = (1e-9 + self.batch_sum2[i]
+                - self.batch_sum[i] * self.batch_sum[i] / self.batch_count)
+                .sqrt()
+                / self.batch_count;
+        }
+    }
+}
+
+/// combo stats changed by a batch as (player, combo index, stats)
+type ComboResults = Vec<(usize, usize, ComboStats)>;
+
+/// channels receiving estimates while a calculation runs
+#[derive(Debug, Default)]
+struct Subscribers {
+    /// open channels with their interval and when they were last sent to
+    channels: Vec<(Sender<Progress>, Duration, Option<Instant>)>,
+    /// the calculation finished and the channels were closed
+    closed: bool,
+}
+
+/// monte carlo batches waiting for earlier batches to finish
+#[derive(Debug)]
+pub(crate) struct PendingBatches<T> {
+    /// index of the next batch to merge into the results
+    next: u64,
+    /// finished batches with their combo results
+    batches: BTreeMap<u64, T>,
+}
+
+impl<T> Default for PendingBatches<T> {
+    fn default() -> Self {
+        PendingBatches {
+            next: 0,
+            batches: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> PendingBatches<T> {
+    /// Add a finished batch
+    pub(crate) fn insert(&mut self, batch_idx: u64, batch: T) {
+        self.batches.insert(batch_idx, batch);
+    }
+
+    /// Take the next batch in index order, if it has finished
+    pub(crate) fn pop_next(&mut self) -> Option<T> {
+        let batch = self.batches.remove(&self.next)?;
+        self.next += 1;
+        Some(batch)
+    }
 }
 
 /// equity calculator main structure
 #[derive(Debug)]
-struct Simulator {
+pub(super) struct Simulator {
     hand_ranges: Vec<HandRange>,
     /// used to reduce rejection sampling
     combined_ranges: Vec<CombinedRange>,
     /// initial board as 64bit mask
     board_mask: u64,
+    /// cards out of play as 64bit mask
+    dead_mask: u64,
     /// initial board used for evaluating
     fixed_board: Hand,
     /// number of players
     n_players: usize,
     /// has monte carlo sim stopped
     stopped: AtomicCell<bool>,
+    /// why the calculation stopped, if it stopped early
+    stop_reason: AtomicCell<Option<StopReason>>,
     /// final results
     results: RwLock<SimulationResults>,
     /// lookup table used for preflop combo -> results
-    lookup_table: RwLock<HashMap<(u64, u64), SimulationResultsBatch>>,
-    /// target stdev from each batch for monte carlo
-    stdev_target: f64,
+    /// keyed by preflop id, suit transformed dead cards
+    /// and the bits of the combined combo weight
+    lookup_table: RwLock<HashMap<(u64, u64, u64), SimulationResultsBatch>>,
+    /// targets and budgets for monte carlo
+    stopping_rules: StoppingRules,
+    /// wall clock budget for monte carlo
+    time_limit: Option<Duration>,
+    /// when the simulator was set up
+    start_time: Instant,
     /// should calculate exact equity
     calc_exact: bool,
+    /// should calculate the equity of each combo
+    combo_equity: bool,
+    /// use a short deck and short deck hand ranks
+    short_deck: bool,
+    /// how monte carlo deals the rest of the board
+    board_sampling: BoardSampling,
+    /// deal the first range's combos by weight, spread evenly over each batch
+    stratify_combos: bool,
+    /// mask of the players who can win, the others are dealt but never win
+    contenders: u32,
     /// preflop combo position for exact equity calculation
     enum_pos: Mutex<u64>,
+    /// seed for monte carlo batches
+    seed: u64,
+    /// index of the next monte carlo batch to run
+    next_batch: AtomicCell<u64>,
+    /// finished monte carlo batches not merged yet
+    pending_batches: Mutex<PendingBatches<(SimulationResultsBatch, ComboResults)>>,
+    /// results of multi board monte carlo simulations
+    multi_board_results: Mutex<(MultiBoardStats, PendingBatches<MultiBoardStats>)>,
+    /// channels receiving estimates
+    subscribers: Mutex<Subscribers>,
+    /// worker threads that haven't finished
+    workers_left: AtomicCell<usize>,
 }
 
 impl Simulator {
+    /// Validate the ranges and set up a simulator for them
+    pub(super) fn prepare(
+        hand_ranges: &[HandRange],
+        options: &EquityOptions,
+        calc_exact: bool,
+    ) -> Result<Simulator, SimulatorError> {
+        if hand_ranges.len() < MIN_PLAYERS {
+            return Err(SimulatorError::TooFewPlayers);
+        }
+        if hand_ranges.len() > MAX_PLAYERS {
+            return Err(SimulatorError::TooManyPlayers);
+        }
+        if options.board_mask.count_ones() > BOARD_CARDS {
+            return Err(SimulatorError::TooManyBoardCards);
+        }
+        // cards missing from a short deck are treated as dead cards
+        let options = &if options.short_deck {
+            if (options.board_mask & SHORT_DECK_REMOVED_CARDS) != 0 {
+                return Err(SimulatorError::CardNotInDeck);
+            }
+            EquityOptions {
+                dead_mask: options.dead_mask | SHORT_DECK_REMOVED_CARDS,
+                ..options.clone()
+            }
+        } else {
+            options.clone()
+        };
+        let cards_needed = (options.board_mask | options.dead_mask).count_ones()
+            + (BOARD_CARDS - options.board_mask.count_ones())
+            + 2 * hand_ranges.len() as u32;
+        if cards_needed > u32::from(CARD_COUNT) {
+            return Err(SimulatorError::TooManyDeadCards);
+        }
+
+        let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut hand_ranges = hand_ranges.to_owned();
+        hand_ranges
+            .iter_mut()
+            .for_each(|h| h.remove_conflicting_combos(options.board_mask | options.dead_mask));
+        let mut combined_ranges = CombinedRange::from_ranges(&hand_ranges);
+        for cr in &mut combined_ranges {
+            if cr.size() == 0 {
+                return Err(SimulatorError::ConflictingRanges);
+            }
+            if !calc_exact {
+                cr.shuffle(&mut rng);
+            }
+        }
+        if calc_exact
+            && combined_ranges
+                .iter()
+                .try_fold(1u64, |count, cr| count.checked_mul(cr.size() as u64))
+                .is_none()
+        {
+            return Err(SimulatorError::TooManyCombos);
+        }
+        Ok(Simulator::new(
+            hand_ranges,
+            combined_ranges,
+            options,
+            calc_exact,
+            seed,
+        ))
+    }
+
     fn new(
         hand_ranges: Vec<HandRange>,
         combined_ranges: Vec<CombinedRange>,
-        board_mask: u64,
+        options: &EquityOptions,
         calc_exact: bool,
-        stdev_target: f64,
+        seed: u64,
     ) -> Simulator {
-        let fixed_board = Hand::from_bit_mask(board_mask);
+        let fixed_board = Hand::from_bit_mask(options.board_mask);
         let n_players = hand_ranges.len();
         Simulator {
             hand_ranges,
             combined_ranges,
-            board_mask,
+            board_mask: options.board_mask,
+            dead_mask: options.dead_mask,
             fixed_board,
             n_players,
             calc_exact,
+            combo_equity: options.combo_equity,
+            short_deck: options.short_deck,
+            board_sampling: options.board_sampling,
+            stratify_combos: options.stratify_combos,
+            contenders: (1 << n_players) - 1,
             stopped: AtomicCell::new(false),
+            stop_reason: AtomicCell::new(None),
             enum_pos: Mutex::new(0u64),
-            results: RwLock::new(SimulationResults::init(n_players)),
+            seed,
+            next_batch: AtomicCell::new(0u64),
+            pending_batches: Mutex::new(PendingBatches::default()),
+            multi_board_results: Mutex::new((
+                MultiBoardStats::init(n_players),
+                PendingBatches::default(),
+            )),
+            subscribers: Mutex::new(Subscribers::default()),
+            workers_left: AtomicCell::new(0),
+            results: RwLock::new(SimulationResults::init(n_players, options.combo_equity)),
             lookup_table: RwLock::new(HashMap::new()),
-            stdev_target,
+            stopping_rules: StoppingRules::new(options),
+            time_limit: options.time_limit,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Stop all threads, keeping the results so far
+    pub(super) fn cancel(&self) {
+        self.stop(StopReason::Cancelled);
+    }
+
+    /// Stop all threads, only the first reason given is kept
+    fn stop(&self, reason: StopReason) {
+        let _ = self.stop_reason.compare_exchange(None, Some(reason));
+        self.stopped.store(true);
+    }
+
+    pub(super) fn is_cancelled(&self) -> bool {
+        self.stop_reason.load() == Some(StopReason::Cancelled)
+    }
+
+    pub(super) fn is_exact(&self) -> bool {
+        self.calc_exact
+    }
+
+    /// Only let the players in `contenders` win, the others are still dealt a hand
+    pub(super) fn set_contenders(&mut self, contenders: u32) {
+        self.contenders = contenders & ((1 << self.n_players) - 1);
+    }
+
+    /// Set how many workers will call `run_worker`
+    pub(super) fn set_worker_count(&self, n_workers: usize) {
+        self.workers_left.store(n_workers);
+        if n_workers == 0 {
+            self.publish(true);
+        }
+    }
+
+    /// Run one worker of the calculation
+    ///
+    /// Subscribers get the final estimate and their channels close
+    /// when the last worker finishes
+    pub(super) fn run_worker(&self, run: fn(&Simulator)) {
+        // close the channels even if the worker panics
+        struct Finish<'a>(&'a Simulator);
+        impl Drop for Finish<'_> {
+            fn drop(&mut self) {
+                if self.0.workers_left.fetch_sub(1) == 1 {
+                    self.0.publish(true);
+                }
+            }
         }
+        let _finish = Finish(self);
+        run(self);
     }
 
-    fn enumerate_all(&self) {
+    /// Get a channel receiving an estimate at most every `interval`
+    pub(super) fn subscribe(&self, interval: Duration) -> Receiver<Progress> {
+        let (sender, receiver) = channel::unbounded();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.closed {
+            // the calculation is over, send the final estimate and close the channel
+            let _ = sender.send(self.progress());
+        } else {
+            subscribers.channels.push((sender, interval, None));
+        }
+        receiver
+    }
+
+    /// Send the current estimate to every subscriber whose interval has passed
+    fn publish(&self, finished: bool) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if finished {
+            subscribers.closed = true;
+        }
+        if subscribers.channels.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let mut progress = None;
+        subscribers
+            .channels
+            .retain_mut(|(sender, interval, last_sent)| {
+                if !finished && last_sent.is_some_and(|t| now.duration_since(t) < *interval) {
+                    return true;
+                }
+                *last_sent = Some(now);
+                let progress = progress.get_or_insert_with(|| self.progress());
+                sender.send(progress.clone()).is_ok() && !finished
+            });
+    }
+
+    pub(super) fn progress(&self) -> Progress {
+        let total = if self.calc_exact {
+            self.get_preflop_combo_count()
+        } else {
+            0
+        };
+        let position = if self.calc_exact {
+            *self.enum_pos.lock().unwrap()
+        } else {
+            0
+        };
+        let results = self.results.read().unwrap();
+        let fraction = if self.calc_exact {
+            position as f64 / total as f64
+        } else {
+            self.monte_carlo_fraction(&results)
+        };
+        Progress {
+            position,
+            total,
+            fraction,
+            eval_count: results.eval_count,
+            equities: results.get_equity(),
+            stdev: results.stdev.clone(),
+        }
+    }
+
+    /// Fraction of the monte carlo run done, judged by whichever
+    /// stopping rule is closest to ending it
+    fn monte_carlo_fraction(&self, results: &SimulationResults) -> f64 {
+        let mut fraction = 0f64;
+        if results.batch_count >= 2.0 {
+            // stdev falls with the square root of the number of batches
+            fraction = fraction.max((self.stopping_rules.stdev_target / results.stdev[0]).powi(2));
+            if let Some(target) = self.stopping_rules.confidence_target {
+                let worst_stdev = results.stdev.iter().cloned().fold(0.0, f64::max);
+                fraction = fraction.max((target / (CONFIDENCE_Z * worst_stdev)).powi(2));
+            }
+        }
+        if let Some(time_limit) = self.time_limit {
+            fraction =
+                fraction.max(self.start_time.elapsed().as_secs_f64() / time_limit.as_secs_f64());
+        }
+        if let Some(max) = self.stopping_rules.max_evaluations {
+            fraction = fraction.max(results.eval_count as f64 / max as f64);
+        }
+        if let Some(max) = self.stopping_rules.max_batches {
+            fraction = fraction.max(results.batch_count / max as f64);
+        }
+        fraction.min(1.0)
+    }
+
+    pub(super) fn get_result(&self) -> EquityResult {
+        let results = self.results.read().unwrap();
+        let (wins, ties, wins_by_mask) = results.get_wins_and_ties();
+        EquityResult {
+            equities: results.get_equity(),
+            wins,
+            ties,
+            wins_by_mask,
+            eval_count: results.eval_count,
+            exact: self.calc_exact && !self.is_cancelled(),
+            stop_reason: self.stop_reason(),
+            stdev: results.stdev.clone(),
+            combo_equities: results.get_combo_equities(&self.hand_ranges),
+        }
+    }
+
+    /// per thread combo stats, empty if combo equity is not calculated
+    fn init_combo_stats(&self) -> Vec<Vec<ComboStats>> {
+        if self.combo_equity {
+            vec![vec![ComboStats::default(); COMBO_COUNT]; self.n_players]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Add the results of a single preflop matchup to the combo stats
+    fn record_batch_combo_stats(
+        &self,
+        combo_stats: &mut [Vec<ComboStats>],
+        hole_cards: &[(u8, u8, f32)],
+        batch: &SimulationResultsBatch,
+    ) {
+        batch.wins_by_mask.for_each(|mask, w| {
+            let mut player_mask = 0;
+            for j in 0..self.n_players {
+                if (mask & (1 << j)) != 0 {
+                    player_mask |= 1 << batch.player_ids[j];
+                }
+            }
+            record_combo_stats(combo_stats, hole_cards, player_mask, w);
+        });
+    }
+
+    fn update_combo_results(&self, combo_stats: &[Vec<ComboStats>]) {
+        if combo_stats.is_empty() {
+            return;
+        }
+        let mut results = self.results.write().unwrap();
+        for (total, local) in results.combo_stats.iter_mut().zip(combo_stats) {
+            for (a, b) in total.iter_mut().zip(local) {
+                a.add(b);
+            }
+        }
+    }
+
+    pub(super) fn enumerate_all(&self) {
         let mut enum_pos = 0u64;
         let mut enum_end = 0u64;
         let mut stats = SimulationResultsBatch::init(self.n_players);
-        let fast_dividers: Vec<DividerU64> = self
-            .combined_ranges
-            .iter()
-            .map(|c| DividerU64::divide_by(c.size() as u64))
-            .collect();
+        let mut combo_stats = self.init_combo_stats();
+        let fast_dividers = self.fast_dividers();
         // let preflop_combos = self.get_preflop_combo_count();
         let postflop_combos = self.get_postflop_combo_count();
-        let use_lookup = postflop_combos > 500;
+        // cached results don't know which sorted seat belongs to a contender
+        let use_lookup = postflop_combos > 500
+            && self.n_players <= LOOKUP_MAX_PLAYERS
+            && self.contenders.count_ones() as usize == self.n_players;
 
         // let randomize_order = postflop_combos > 10000 && preflop_combos <= 2 * MAX_LOOKUP_SIZE;
         loop {
@@ -360,48 +1195,37 @@ impl Simulator {
                 }
             }
 
-            let mut rand_enum_pos = enum_pos;
-
-            let mut ok = true;
-            let mut used_cards_mask = self.board_mask;
             let mut player_hands = [HandWithIndex::default(); MAX_PLAYERS];
-            for i in 0..self.combined_ranges.len() {
-                let quotient = fast_dividers[i].divide(rand_enum_pos);
-                let remainder = rand_enum_pos - quotient * self.combined_ranges[i].size() as u64;
-                rand_enum_pos = quotient;
-                let combo = &self.combined_ranges[i].combos()[remainder as usize];
-                if (used_cards_mask & combo.mask) != 0 {
-                    ok = false;
-                    break;
-                }
-                used_cards_mask |= combo.mask;
-                for j in 0..self.combined_ranges[i].player_count() {
-                    let player_idx = self.combined_ranges[i].players()[j];
-                    player_hands[player_idx].cards = combo.hole_cards[player_idx];
-                    player_hands[player_idx].player_idx = player_idx;
-                }
-            }
-
-            if ok {
-                let mut weight = 1u64;
+            let mut hole_cards = [(52u8, 52u8, 0f32); MAX_PLAYERS];
+            if let Some(mut used_cards_mask) =
+                self.deal_matchup(enum_pos, &fast_dividers, &mut player_hands, &mut hole_cards)
+            {
+                let mut weight = 1f64;
                 for hand in &player_hands[0..self.n_players] {
-                    weight *= u64::from(hand.cards.2);
+                    weight *= f64::from(hand.cards.2);
                 }
                 let mut board_mask = self.board_mask;
+                let mut dead_mask = self.dead_mask;
                 if use_lookup {
                     player_hands[0..self.n_players].sort();
                     for i in 0..self.n_players {
                         stats.player_ids[i] = player_hands[i].player_idx;
                     }
-                    self.transform_suits(&mut player_hands, self.n_players, &mut board_mask);
-                    used_cards_mask = board_mask;
+                    self.transform_suits(
+                        &mut player_hands,
+                        self.n_players,
+                        &mut board_mask,
+                        &mut dead_mask,
+                    );
+                    used_cards_mask = board_mask | dead_mask;
                     for j in 0..self.n_players {
                         used_cards_mask |=
                             (1u64 << player_hands[j].cards.0) | (1u64 << player_hands[j].cards.1);
                     }
 
                     let preflop_id = calculate_preflop_id(&player_hands, self.n_players);
-                    if self.lookup_results((preflop_id, weight), &mut stats) {
+                    let lookup_id = (preflop_id, dead_mask, weight.to_bits());
+                    if self.lookup_results(lookup_id, &mut stats) {
                         for i in 0..self.n_players {
                             stats.player_ids[i] = player_hands[i].player_idx;
                         }
@@ -417,8 +1241,22 @@ impl Simulator {
                             used_cards_mask,
                             &mut stats,
                         );
-                        self.store_results((preflop_id, weight), &stats);
+                        self.store_results(lookup_id, &stats);
+                    }
+                    if self.combo_equity {
+                        self.record_batch_combo_stats(&mut combo_stats, &hole_cards, &stats);
                     }
+                } else if self.combo_equity {
+                    let mut combo_batch = SimulationResultsBatch::init(self.n_players);
+                    self.enumerate_board(
+                        &player_hands,
+                        weight,
+                        &self.fixed_board,
+                        used_cards_mask,
+                        &mut combo_batch,
+                    );
+                    self.record_batch_combo_stats(&mut combo_stats, &hole_cards, &combo_batch);
+                    stats.merge(&combo_batch);
                 } else {
                     // stats.unique_preflop_combos += 1;
                     self.enumerate_board(
@@ -442,28 +1280,238 @@ impl Simulator {
         }
 
         self.update_results(&stats, true);
+        self.update_combo_results(&combo_stats);
     }
 
-    fn lookup_results(&self, id: (u64, u64), stats: &mut SimulationResultsBatch) -> bool {
-        let table = self.lookup_table.read().unwrap();
-        match table.get(&id) {
+    /// Enumerate every runout of every matchup, keeping the results
+    /// of each next board card apart
+    ///
+    /// Returns the stats of each card indexed by card
+    pub(super) fn enumerate_next_cards(&self) -> Vec<CardStats> {
+        let mut enum_pos = 0u64;
+        let mut enum_end = 0u64;
+        let mut card_batches = vec![SimulationResultsBatch::init(self.n_players); 52];
+        let fast_dividers = self.fast_dividers();
+        let postflop_combos = self.get_postflop_combo_count();
+        loop {
+            if enum_pos >= enum_end {
+                let batch_size = std::cmp::max(2000000 / postflop_combos, 1);
+                let (p, e) = self.reserve_batch(batch_size);
+                enum_pos = p;
+                enum_end = e;
+                if enum_pos >= enum_end {
+                    break;
+                }
+            }
+
+            let mut player_hands = [HandWithIndex::default(); MAX_PLAYERS];
+            let mut hole_cards = [(52u8, 52u8, 0f32); MAX_PLAYERS];
+            if let Some(used_cards_mask) =
+                self.deal_matchup(enum_pos, &fast_dividers, &mut player_hands, &mut hole_cards)
+            {
+                let mut weight = 1f64;
+                for hand in &player_hands[0..self.n_players] {
+                    weight *= f64::from(hand.cards.2);
+                }
+                for card in 0..CARD_COUNT {
+                    let card_mask = 1u64 << card;
+                    if (used_cards_mask & card_mask) != 0 {
+                        continue;
+                    }
+                    self.enumerate_board(
+                        &player_hands,
+                        weight,
+                        &(self.fixed_board + CARDS[usize::from(card)]),
+                        used_cards_mask | card_mask,
+                        &mut card_batches[usize::from(card)],
+                    );
+                }
+            }
+            enum_pos += 1;
+        }
+
+        card_batches
+            .iter()
+            .map(|batch| CardStats::from_batch(batch, self.n_players))
+            .collect()
+    }
+
+    /// Make sure the deck holds enough cards to deal `n_boards` boards
+    pub(super) fn check_board_count(&self, n_boards: usize) -> Result<(), SimulatorError> {
+        if n_boards == 0 {
+            return Err(SimulatorError::NoBoards);
+        }
+        let cards_needed = (self.board_mask | self.dead_mask).count_ones() as usize
+            + n_boards * (BOARD_CARDS - self.fixed_board.count()) as usize
+            + 2 * self.n_players;
+        if cards_needed > usize::from(CARD_COUNT) {
+            return Err(SimulatorError::TooManyDeadCards);
+        }
+        Ok(())
+    }
+
+    /// Enumerate every matchup against every way of dealing `n_boards`
+    /// boards without replacement
+    pub(super) fn enumerate_multi_board(&self, n_boards: usize) -> MultiBoardStats {
+        let mut enum_pos = 0u64;
+        let mut enum_end = 0u64;
+        let mut stats = MultiBoardStats::init(self.n_players);
+        let fast_dividers = self.fast_dividers();
+        let postflop_combos = self
+            .get_postflop_combo_count()
+            .saturating_pow(n_boards as u32);
+        let mut winner_masks = Vec::with_capacity(n_boards);
+        loop {
+            if enum_pos >= enum_end {
+                let batch_size = std::cmp::max(2000000 / postflop_combos, 1);
+                let (p, e) = self.reserve_batch(batch_size);
+                enum_pos = p;
+                enum_end = e;
+                if enum_pos >= enum_end {
+                    break;
+                }
+            }
+
+            let mut player_hands = [HandWithIndex::default(); MAX_PLAYERS];
+            let mut hole_cards = [(52u8, 52u8, 0f32); MAX_PLAYERS];
+            if let Some(used_cards_mask) =
+                self.deal_matchup(enum_pos, &fast_dividers, &mut player_hands, &mut hole_cards)
+            {
+                let mut weight = 1f64;
+                for hand in &player_hands[0..self.n_players] {
+                    weight *= f64::from(hand.cards.2);
+                }
+                self.enumerate_boards(
+                    &player_hands,
+                    weight,
+                    used_cards_mask,
+                    n_boards,
+                    &mut winner_masks,
+                    &mut stats,
+                );
+            }
+            enum_pos += 1;
+        }
+        stats
+    }
+
+    /// Deal each of the remaining boards in turn, the last one through `enumerate_board`
+    fn enumerate_boards(
+        &self,
+        player_hands: &[HandWithIndex],
+        weight: f64,
+        used_cards_mask: u64,
+        boards_left: usize,
+        winner_masks: &mut Vec<u32>,
+        stats: &mut MultiBoardStats,
+    ) {
+        if boards_left == 1 {
+            let mut batch = SimulationResultsBatch::init(self.n_players);
+            self.enumerate_board(
+                player_hands,
+                weight,
+                &self.fixed_board,
+                used_cards_mask,
+                &mut batch,
+            );
+            stats.eval_count += batch.eval_count;
+            batch.wins_by_mask.for_each(|mask, w| {
+                winner_masks.push(mask);
+                stats.add(winner_masks, w);
+                winner_masks.pop();
+            });
+            return;
+        }
+        let mut hands = [Hand::default(); MAX_PLAYERS];
+        for i in 0..self.n_players {
+            hands[i] = Hand::from_hole_cards(player_hands[i].cards.0, player_hands[i].cards.1);
+        }
+        let deck: Vec<u8> = (0..CARD_COUNT)
+            .filter(|c| (used_cards_mask >> c) & 1 == 0)
+            .collect();
+        let mut scratch = SimulationResultsBatch::init(self.n_players);
+        let cards_remaining = BOARD_CARDS - self.fixed_board.count();
+        for_each_card_subset(&deck, cards_remaining, 0, 0, &mut |runout| {
+            let mut board = self.fixed_board;
+            let mut cards = runout;
+            while cards != 0 {
+                board += CARDS[cards.trailing_zeros() as usize];
+                cards &= cards - 1;
+            }
+            let winner_mask = self.evaluate_hands(&hands, weight, &board, &mut scratch, true);
+            winner_masks.push(winner_mask as u32);
+            self.enumerate_boards(
+                player_hands,
+                weight,
+                used_cards_mask | runout,
+                boards_left - 1,
+                winner_masks,
+                stats,
+            );
+            winner_masks.pop();
+        });
+        stats.eval_count += scratch.eval_count;
+    }
+
+    /// Fast dividers for the size of each combined range
+    fn fast_dividers(&self) -> Vec<DividerU64> {
+        self.combined_ranges
+            .iter()
+            .map(|c| DividerU64::divide_by(c.size() as u64))
+            .collect()
+    }
+
+    /// Deal the matchup at position `enum_pos` of the enumeration
+    ///
+    /// Returns the mask of used cards or `None` if the combos conflict
+    fn deal_matchup(
+        &self,
+        enum_pos: u64,
+        fast_dividers: &[DividerU64],
+        player_hands: &mut [HandWithIndex],
+        hole_cards: &mut [(u8, u8, f32)],
+    ) -> Option<u64> {
+        let mut rand_enum_pos = enum_pos;
+        let mut used_cards_mask = self.board_mask | self.dead_mask;
+        for i in 0..self.combined_ranges.len() {
+            let quotient = fast_dividers[i].divide(rand_enum_pos);
+            let remainder = rand_enum_pos - quotient * self.combined_ranges[i].size() as u64;
+            rand_enum_pos = quotient;
+            let combo = &self.combined_ranges[i].combos()[remainder as usize];
+            if (used_cards_mask & combo.mask) != 0 {
+                return None;
+            }
+            used_cards_mask |= combo.mask;
+            for j in 0..self.combined_ranges[i].player_count() {
+                let player_idx = self.combined_ranges[i].players()[j];
+                player_hands[player_idx].cards = combo.hole_cards[j];
+                player_hands[player_idx].player_idx = player_idx;
+                hole_cards[player_idx] = combo.hole_cards[j];
+            }
+        }
+        Some(used_cards_mask)
+    }
+
+    fn lookup_results(&self, id: (u64, u64, u64), stats: &mut SimulationResultsBatch) -> bool {
+        let table = self.lookup_table.read().unwrap();
+        match table.get(&id) {
             Some(s) => {
-                *stats = *s;
+                *stats = s.clone();
                 true
             }
             None => false,
         }
     }
 
-    fn store_results(&self, id: (u64, u64), stats: &SimulationResultsBatch) {
+    fn store_results(&self, id: (u64, u64, u64), stats: &SimulationResultsBatch) {
         let mut table = self.lookup_table.write().unwrap();
-        table.insert(id, *stats);
+        table.insert(id, stats.clone());
     }
 
     fn enumerate_board(
         &self,
         player_hands: &[HandWithIndex],
-        weight: u64,
+        weight: f64,
         board: &Hand,
         used_cards_mask: u64,
         stats: &mut SimulationResultsBatch,
@@ -527,7 +1575,7 @@ impl Simulator {
         suit_counts: &mut [u8],
         cards_remaining: u8,
         start: usize,
-        weight: u64,
+        weight: f64,
     ) {
         if cards_remaining == 1 {
             if (suit_counts[0] < 4)
@@ -537,12 +1585,12 @@ impl Simulator {
             {
                 let mut i = start;
                 while i < n_deck {
-                    let mut multiplier = 1;
+                    let mut multiplier = 1f64;
                     let new_board = *board + CARDS[usize::from(deck[i])];
                     let rank = deck[i] >> 2;
                     i += 1;
                     while i < n_deck && deck[i] >> 2 == rank {
-                        multiplier += 1;
+                        multiplier += 1.0;
                         i += 1;
                     }
                     self.evaluate_hands(hands, weight * multiplier, &new_board, stats, false);
@@ -550,7 +1598,7 @@ impl Simulator {
             } else {
                 let mut last_rank = u8::MAX;
                 for i in start..n_deck {
-                    let mut multiplier = 1;
+                    let mut multiplier = 1f64;
                     if suit_counts[usize::from(deck[i] & 3)] < 4 {
                         let rank = deck[i] >> 2;
                         if rank == last_rank {
@@ -561,7 +1609,7 @@ impl Simulator {
                                 break;
                             }
                             if suit_counts[usize::from(deck[j] & 3)] < 4 {
-                                multiplier += 1;
+                                multiplier += 1.0;
                             }
                         }
                         last_rank = rank;
@@ -594,12 +1642,12 @@ impl Simulator {
 
                 for repeats in 1..std::cmp::min(irrelevant_count, usize::from(cards_remaining)) + 1
                 {
-                    const BINOM_COEFF: [[u64; 5]; 5] = [
-                        [0, 0, 0, 0, 0],
-                        [0, 1, 0, 0, 0],
-                        [1, 2, 1, 0, 0],
-                        [1, 3, 3, 1, 0],
-                        [1, 4, 6, 4, 1],
+                    const BINOM_COEFF: [[f64; 5]; 5] = [
+                        [0.0, 0.0, 0.0, 0.0, 0.0],
+                        [0.0, 1.0, 0.0, 0.0, 0.0],
+                        [1.0, 2.0, 1.0, 0.0, 0.0],
+                        [1.0, 3.0, 3.0, 1.0, 0.0],
+                        [1.0, 4.0, 6.0, 4.0, 1.0],
                     ];
                     let new_weight = BINOM_COEFF[irrelevant_count][repeats] * weight;
                     new_board += CARDS[usize::from(deck[i + repeats - 1])];
@@ -647,6 +1695,7 @@ impl Simulator {
         player_hands: &mut [HandWithIndex],
         n_players: usize,
         board_mask: &mut u64,
+        dead_mask: &mut u64,
     ) -> u8 {
         let mut transform = [u8::MAX; 4];
         let mut suit_count = 0;
@@ -680,6 +1729,18 @@ impl Simulator {
             player_hands[i].cards.1 =
                 (player_hands[i].cards.1 & RANK_MASK) | transform[usize::from(suit)];
         }
+        let mut new_dead_cards = 0u64;
+        for i in 0..CARD_COUNT {
+            if ((*dead_mask >> i) & 1) != 0 {
+                let suit = i & SUIT_MASK;
+                if transform[usize::from(suit)] == u8::MAX {
+                    transform[usize::from(suit)] = suit_count;
+                    suit_count += 1;
+                }
+                new_dead_cards |= 1u64 << ((i & RANK_MASK) | transform[usize::from(suit)]);
+            }
+        }
+        *dead_mask = new_dead_cards;
 
         suit_count
     }
@@ -687,6 +1748,9 @@ impl Simulator {
     fn reserve_batch(&self, batch_size: u64) -> (u64, u64) {
         let total_batch_count = self.get_preflop_combo_count();
         let mut enum_pos = self.enum_pos.lock().unwrap();
+        if self.stopped.load() {
+            return (*enum_pos, *enum_pos);
+        }
         let start = *enum_pos;
         let end = std::cmp::min(total_batch_count, *enum_pos + batch_size);
         *enum_pos = end;
@@ -704,6 +1768,7 @@ impl Simulator {
     fn get_postflop_combo_count(&self) -> u64 {
         let mut cards_in_deck = u64::from(CARD_COUNT);
         cards_in_deck -= u64::from(self.fixed_board.count());
+        cards_in_deck -= u64::from(self.dead_mask.count_ones());
         cards_in_deck -= 2 * self.n_players as u64;
         let board_cards_remaining = 5 - u64::from(self.fixed_board.count());
         let mut postflop_combos = 1u64;
@@ -716,9 +1781,17 @@ impl Simulator {
         postflop_combos
     }
 
-    fn sim_random_walk_monte_carlo<R: Rng>(&self, rng: &mut R) {
-        let mut batch = SimulationResultsBatch::init(self.n_players);
-        let card_dist: Uniform<u8> = Uniform::from(0..CARD_COUNT);
+    pub(super) fn sim_random_walk_monte_carlo(&self) {
+        if self.board_sampling != BoardSampling::Random || self.stratify_combos {
+            self.sim_variance_reduced_monte_carlo();
+            return;
+        }
+        let first_card = if self.short_deck {
+            SHORT_DECK_REMOVED_CARDS.count_ones() as u8
+        } else {
+            0
+        };
+        let card_dist: Uniform<u8> = Uniform::from(first_card..CARD_COUNT);
         let combo_dists: Vec<Uniform<usize>> = (0..self.combined_ranges.len())
             .into_iter()
             .map(|i| Uniform::from(0..self.combined_ranges[i].size()))
@@ -727,75 +1800,448 @@ impl Simulator {
         let mut used_cards_mask = 0u64;
         let mut player_hands = [Hand::default(); MAX_PLAYERS];
         let mut combo_indexes = [0usize; MAX_PLAYERS];
-        let mut combo_weights = [1u8; MAX_PLAYERS];
+        let mut hole_cards = [(52u8, 52u8, 0f32); MAX_PLAYERS];
+        let mut combo_stats = self.init_combo_stats();
+        let mut touched_combos = Vec::new();
         let cards_remaining = 5 - self.fixed_board.count();
 
-        if self.randomize_hole_cards(
-            &mut used_cards_mask,
-            &mut combo_indexes,
-            &mut player_hands,
-            &mut combo_weights,
-            rng,
-            &combo_dists,
-        ) {
-            loop {
+        while !self.stopped.load() {
+            if let Some(time_limit) = self.time_limit {
+                if self.start_time.elapsed() >= time_limit {
+                    self.stop(StopReason::TimeLimit);
+                    break;
+                }
+            }
+            // every batch has its own random stream so results
+            // don't depend on which thread runs it
+            let batch_idx = self.next_batch.fetch_add(1);
+            let mut rng = SmallRng::seed_from_u64(batch_seed(self.seed, batch_idx));
+            let mut batch = SimulationResultsBatch::init(self.n_players);
+            if !self.randomize_hole_cards(
+                &mut used_cards_mask,
+                &mut combo_indexes,
+                &mut player_hands,
+                &mut hole_cards,
+                &mut rng,
+                &combo_dists,
+            ) {
+                self.stop(StopReason::NoValidDeal);
+                break;
+            }
+            while batch.eval_count < MONTE_CARLO_BATCH_SIZE {
                 let mut board = self.fixed_board;
-                let mut weight = 1u64;
-                for c in &combo_weights {
-                    weight *= u64::from(*c);
+                let mut weight = 1f64;
+                for h in &hole_cards[0..self.n_players] {
+                    weight *= f64::from(h.2);
                 }
                 randomize_board(
-                    rng,
+                    &mut rng,
                     &mut board,
                     used_cards_mask,
                     cards_remaining,
                     &card_dist,
                 );
-                self.evaluate_hands(&player_hands, weight, &board, &mut batch, true);
-
-                if (batch.eval_count & 0xfff) == 0 {
-                    self.update_results(&batch, false);
-                    if self.stopped.load() {
-                        break;
+                let winner_mask =
+                    self.evaluate_hands(&player_hands, weight, &board, &mut batch, true);
+                if self.combo_equity {
+                    for (i, h) in hole_cards[0..self.n_players].iter().enumerate() {
+                        let idx = combo_index(h.0, h.1);
+                        if combo_stats[i][idx].total == 0.0 {
+                            touched_combos.push((i, idx));
+                        }
                     }
-                    batch = SimulationResultsBatch::init(self.n_players);
-                    if !self.randomize_hole_cards(
+                    record_combo_stats(&mut combo_stats, &hole_cards, winner_mask, weight);
+                }
+
+                self.random_walk_step(
+                    combined_range_dist.sample(&mut rng),
+                    &mut used_cards_mask,
+                    &mut combo_indexes,
+                    &mut player_hands,
+                    &mut hole_cards,
+                );
+            }
+            let combo_results = touched_combos
+                .drain(..)
+                .map(|(i, idx)| (i, idx, std::mem::take(&mut combo_stats[i][idx])))
+                .collect();
+            self.submit_batch(batch_idx, batch, combo_results);
+        }
+    }
+
+    /// Monte carlo simulation dealing `n_boards` boards from the same deck
+    pub(super) fn sim_multi_board_monte_carlo(&self, n_boards: usize) {
+        let first_card = if self.short_deck {
+            SHORT_DECK_REMOVED_CARDS.count_ones() as u8
+        } else {
+            0
+        };
+        let card_dist: Uniform<u8> = Uniform::from(first_card..CARD_COUNT);
+        let combo_dists: Vec<Uniform<usize>> = (0..self.combined_ranges.len())
+            .map(|i| Uniform::from(0..self.combined_ranges[i].size()))
+            .collect();
+        let mut used_cards_mask = 0u64;
+        let mut player_hands = [Hand::default(); MAX_PLAYERS];
+        let mut combo_indexes = [0usize; MAX_PLAYERS];
+        let mut hole_cards = [(52u8, 52u8, 0f32); MAX_PLAYERS];
+        let mut winner_masks = Vec::with_capacity(n_boards);
+        let cards_remaining = BOARD_CARDS - self.fixed_board.count();
+
+        while !self.stopped.load() {
+            if let Some(time_limit) = self.time_limit {
+                if self.start_time.elapsed() >= time_limit {
+                    self.stop(StopReason::TimeLimit);
+                    break;
+                }
+            }
+            let batch_idx = self.next_batch.fetch_add(1);
+            let mut rng = SmallRng::seed_from_u64(batch_seed(self.seed, batch_idx));
+            let mut scratch = SimulationResultsBatch::init(self.n_players);
+            let mut batch = MultiBoardStats::init(self.n_players);
+            while scratch.eval_count < MONTE_CARLO_BATCH_SIZE {
+                if !self.randomize_hole_cards(
+                    &mut used_cards_mask,
+                    &mut combo_indexes,
+                    &mut player_hands,
+                    &mut hole_cards,
+                    &mut rng,
+                    &combo_dists,
+                ) {
+                    self.stop(StopReason::NoValidDeal);
+                    return;
+                }
+                let mut weight = 1f64;
+                for h in &hole_cards[0..self.n_players] {
+                    weight *= f64::from(h.2);
+                }
+                winner_masks.clear();
+                for _ in 0..n_boards {
+                    let mut board = self.fixed_board;
+                    used_cards_mask = randomize_board(
+                        &mut rng,
+                        &mut board,
+                        used_cards_mask,
+                        cards_remaining,
+                        &card_dist,
+                    );
+                    let winner_mask =
+                        self.evaluate_hands(&player_hands, weight, &board, &mut scratch, true);
+                    winner_masks.push(winner_mask as u32);
+                }
+                batch.add(&winner_masks, weight);
+            }
+            batch.eval_count = scratch.eval_count;
+            self.submit_multi_board_batch(batch_idx, batch);
+        }
+    }
+
+    /// Merge finished multi board batches in index order
+    fn submit_multi_board_batch(&self, batch_idx: u64, batch: MultiBoardStats) {
+        let mut guard = self.multi_board_results.lock().unwrap();
+        let (results, pending) = &mut *guard;
+        pending.insert(batch_idx, batch);
+        while !self.stopped.load() {
+            let batch = match pending.pop_next() {
+                Some(b) => b,
+                None => break,
+            };
+            results.merge_batch(&batch);
+            self.check_stopping_rules(results.batch_count, results.eval_count, &results.stdev);
+        }
+    }
+
+    /// Results of the multi board monte carlo simulation
+    pub(super) fn multi_board_results(&self) -> MultiBoardStats {
+        self.multi_board_results.lock().unwrap().0.clone()
+    }
+
+    /// Why the simulation stopped
+    pub(super) fn stop_reason(&self) -> StopReason {
+        self.stop_reason.load().unwrap_or(StopReason::Completed)
+    }
+
+    /// Monte carlo simulation with the board sampling and combo stratification options
+    fn sim_variance_reduced_monte_carlo(&self) {
+        let first_card = if self.short_deck {
+            SHORT_DECK_REMOVED_CARDS.count_ones() as u8
+        } else {
+            0
+        };
+        let combo_dists: Vec<Uniform<usize>> = (0..self.combined_ranges.len())
+            .map(|i| Uniform::from(0..self.combined_ranges[i].size()))
+            .collect();
+        let combined_range_dist = Uniform::from(0..self.combined_ranges.len());
+        let combo_weights: Vec<Vec<f64>> = self
+            .combined_ranges
+            .iter()
+            .map(|c| {
+                c.combos()
+                    .iter()
+                    .map(|combo| combo.hole_cards.iter().map(|h| f64::from(h.2)).product())
+                    .collect()
+            })
+            .collect();
+        let weighted_dists: Option<Vec<WeightedIndex<f64>>> = if self.stratify_combos {
+            match combo_weights.iter().map(WeightedIndex::new).collect() {
+                Ok(dists) => Some(dists),
+                Err(_) => {
+                    self.stop(StopReason::NoValidDeal);
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        // cumulative weights of the first range for systematic sampling
+        let first_cumulative: Vec<f64> = combo_weights[0]
+            .iter()
+            .scan(0f64, |total, w| {
+                *total += w;
+                Some(*total)
+            })
+            .collect();
+        let mut used_cards_mask = 0u64;
+        let mut player_hands = [Hand::default(); MAX_PLAYERS];
+        let mut combo_indexes = [0usize; MAX_PLAYERS];
+        let mut hole_cards = [(52u8, 52u8, 0f32); MAX_PLAYERS];
+        let mut combo_stats = self.init_combo_stats();
+        let mut touched_combos = Vec::new();
+        let cards_remaining = (BOARD_CARDS - self.fixed_board.count()) as usize;
+        let passes = if self.board_sampling == BoardSampling::Antithetic {
+            2
+        } else {
+            1
+        };
+
+        while !self.stopped.load() {
+            if let Some(time_limit) = self.time_limit {
+                if self.start_time.elapsed() >= time_limit {
+                    self.stop(StopReason::TimeLimit);
+                    break;
+                }
+            }
+            let batch_idx = self.next_batch.fetch_add(1);
+            let mut rng = SmallRng::seed_from_u64(batch_seed(self.seed, batch_idx));
+            let mut batch = SimulationResultsBatch::init(self.n_players);
+            let combo_offset: f64 = rng.gen();
+            let mut shift = [0f64; BOARD_CARDS as usize];
+            for s in shift.iter_mut() {
+                *s = rng.gen();
+            }
+            let mut uniforms = [0f64; BOARD_CARDS as usize];
+            let mut sample = 0u64;
+            while batch.eval_count < MONTE_CARLO_BATCH_SIZE {
+                if sample >= MAX_SAMPLES_PER_BATCH {
+                    self.stop(StopReason::NoValidDeal);
+                    return;
+                }
+                let stratum = sample % MONTE_CARLO_BATCH_SIZE;
+                sample += 1;
+
+                let mut weight = 1f64;
+                if let Some(weighted_dists) = &weighted_dists {
+                    // conflicting deals are dropped rather than redealt,
+                    // which keeps the deal in proportion to the weights
+                    let u = (combo_offset + sample as f64 * COMBO_STEP).fract();
+                    let target = u * first_cumulative.last().unwrap();
+                    let first_idx = first_cumulative
+                        .partition_point(|c| *c <= target)
+                        .min(first_cumulative.len() - 1);
+                    if !self.deal_combos(
+                        |i, rng| {
+                            if i == 0 {
+                                first_idx
+                            } else {
+                                weighted_dists[i].sample(rng)
+                            }
+                        },
                         &mut used_cards_mask,
-                        &mut combo_indexes,
                         &mut player_hands,
-                        &mut combo_weights,
-                        rng,
-                        &combo_dists,
+                        &mut hole_cards,
+                        &mut rng,
                     ) {
-                        break;
+                        continue;
+                    }
+                } else {
+                    // same random walk over the combos as plain monte carlo
+                    if sample == 1 {
+                        if !self.randomize_hole_cards(
+                            &mut used_cards_mask,
+                            &mut combo_indexes,
+                            &mut player_hands,
+                            &mut hole_cards,
+                            &mut rng,
+                            &combo_dists,
+                        ) {
+                            self.stop(StopReason::NoValidDeal);
+                            return;
+                        }
+                    } else {
+                        self.random_walk_step(
+                            combined_range_dist.sample(&mut rng),
+                            &mut used_cards_mask,
+                            &mut combo_indexes,
+                            &mut player_hands,
+                            &mut hole_cards,
+                        );
+                    }
+                    for h in &hole_cards[0..self.n_players] {
+                        weight *= f64::from(h.2);
                     }
                 }
 
-                let combined_range_idx = combined_range_dist.sample(rng);
-                let combined_range = &self.combined_ranges[combined_range_idx];
-                let mut combo_idx = combo_indexes[combined_range_idx];
-                used_cards_mask -= combined_range.combos()[combo_idx].mask;
-                let mut mask;
-                loop {
-                    if combo_idx == 0 {
-                        combo_idx = combined_range.size();
+                for pass in 0..passes {
+                    if pass == 0 {
+                        self.board_uniforms(&mut uniforms, &shift, sample, stratum, &mut rng);
+                    } else {
+                        for u in uniforms.iter_mut() {
+                            *u = 1.0 - *u;
+                        }
                     }
-                    combo_idx -= 1;
-                    mask = combined_range.combos()[combo_idx].mask;
-                    if (mask & used_cards_mask) == 0 {
-                        break;
+                    let board = deal_board_from_uniforms(
+                        self.fixed_board,
+                        used_cards_mask,
+                        first_card,
+                        &uniforms[0..cards_remaining],
+                    );
+                    let winner_mask =
+                        self.evaluate_hands(&player_hands, weight, &board, &mut batch, true);
+                    if self.combo_equity {
+                        for (i, h) in hole_cards[0..self.n_players].iter().enumerate() {
+                            let idx = combo_index(h.0, h.1);
+                            if combo_stats[i][idx].total == 0.0 {
+                                touched_combos.push((i, idx));
+                            }
+                        }
+                        record_combo_stats(&mut combo_stats, &hole_cards, winner_mask, weight);
+                    }
+                }
+            }
+            let combo_results = touched_combos
+                .drain(..)
+                .map(|(i, idx)| (i, idx, std::mem::take(&mut combo_stats[i][idx])))
+                .collect();
+            self.submit_batch(batch_idx, batch, combo_results);
+        }
+    }
+
+    /// Move one combined range to its previous combo that fits the deck
+    fn random_walk_step(
+        &self,
+        combined_range_idx: usize,
+        used_cards_mask: &mut u64,
+        combo_indexes: &mut [usize],
+        player_hands: &mut [Hand],
+        hole_cards: &mut [(u8, u8, f32)],
+    ) {
+        let combined_range = &self.combined_ranges[combined_range_idx];
+        let mut combo_idx = combo_indexes[combined_range_idx];
+        *used_cards_mask -= combined_range.combos()[combo_idx].mask;
+        let mut mask;
+        loop {
+            if combo_idx == 0 {
+                combo_idx = combined_range.size();
+            }
+            combo_idx -= 1;
+            mask = combined_range.combos()[combo_idx].mask;
+            if (mask & *used_cards_mask) == 0 {
+                break;
+            }
+        }
+        *used_cards_mask |= mask;
+        for i in 0..combined_range.player_count() {
+            let player_idx = combined_range.players()[i];
+            player_hands[player_idx] = combined_range.combos()[combo_idx].hands[i];
+            hole_cards[player_idx] = combined_range.combos()[combo_idx].hole_cards[i];
+        }
+        combo_indexes[combined_range_idx] = combo_idx;
+    }
+
+    /// Uniform numbers that pick the next board cards of a sample
+    fn board_uniforms<R: Rng>(
+        &self,
+        uniforms: &mut [f64],
+        shift: &[f64],
+        sample: u64,
+        stratum: u64,
+        rng: &mut R,
+    ) {
+        match self.board_sampling {
+            BoardSampling::Random | BoardSampling::Antithetic => {
+                for u in uniforms.iter_mut() {
+                    *u = rng.gen();
+                }
+            }
+            BoardSampling::Stratified => {
+                // scatter the strata so they don't follow the stratified combos
+                let stratum = stratum.wrapping_mul(STRATUM_STRIDE) % MONTE_CARLO_BATCH_SIZE;
+                if let Some((first, rest)) = uniforms.split_first_mut() {
+                    *first = (stratum as f64 + rng.gen::<f64>()) / MONTE_CARLO_BATCH_SIZE as f64;
+                    for u in rest.iter_mut() {
+                        *u = rng.gen();
                     }
                 }
-                used_cards_mask |= mask;
-                for i in 0..combined_range.player_count() {
-                    let player_idx = combined_range.players()[i];
-                    player_hands[player_idx] = combined_range.combos()[combo_idx].hands[i];
-                    combo_weights[player_idx] = combined_range.combos()[combo_idx].hole_cards[i].2;
+            }
+            BoardSampling::QuasiRandom => {
+                for (d, u) in uniforms.iter_mut().enumerate() {
+                    *u = (shift[d] + sample as f64 * QUASI_RANDOM_ALPHA[d]).fract();
+                }
+            }
+        }
+    }
+
+    /// Deal one combo of every combined range, picked by `pick`
+    ///
+    /// Returns false if the combos conflict
+    fn deal_combos<R: Rng, F: Fn(usize, &mut R) -> usize>(
+        &self,
+        pick: F,
+        used_cards_mask: &mut u64,
+        player_hands: &mut [Hand],
+        hole_cards: &mut [(u8, u8, f32)],
+        rng: &mut R,
+    ) -> bool {
+        *used_cards_mask = self.board_mask | self.dead_mask;
+        for i in 0..self.combined_ranges.len() {
+            let combo = &self.combined_ranges[i].combos()[pick(i, rng)];
+            if (*used_cards_mask & combo.mask) != 0 {
+                return false;
+            }
+            for j in 0..self.combined_ranges[i].player_count() {
+                let player_idx = self.combined_ranges[i].players()[j];
+                player_hands[player_idx] = combo.hands[j];
+                hole_cards[player_idx] = combo.hole_cards[j];
+            }
+            *used_cards_mask |= combo.mask;
+        }
+        true
+    }
+
+    /// Merge finished monte carlo batches in index order
+    ///
+    /// Batches after the one that reaches the stdev target are dropped,
+    /// so results only depend on the seed
+    fn submit_batch(
+        &self,
+        batch_idx: u64,
+        batch: SimulationResultsBatch,
+        combo_results: ComboResults,
+    ) {
+        let mut pending = self.pending_batches.lock().unwrap();
+        pending.insert(batch_idx, (batch, combo_results));
+        while !self.stopped.load() {
+            let (batch, combo_results) = match pending.pop_next() {
+                Some(b) => b,
+                None => break,
+            };
+            self.update_results(&batch, false);
+            if !combo_results.is_empty() {
+                let mut results = self.results.write().unwrap();
+                for (i, idx, stats) in &combo_results {
+                    results.combo_stats[*i][*idx].add(stats);
                 }
-                combo_indexes[combined_range_idx] = combo_idx;
             }
         }
-        self.update_results(&batch, true);
     }
 
     fn randomize_hole_cards<R: Rng>(
@@ -803,98 +2249,108 @@ impl Simulator {
         used_cards_mask: &mut u64,
         combo_indexes: &mut [usize],
         player_hands: &mut [Hand],
-        combo_weights: &mut [u8],
+        hole_cards: &mut [(u8, u8, f32)],
         rng: &mut R,
         combo_dists: &[Uniform<usize>],
     ) -> bool {
-        let mut ok;
-        for _ in 0..1000 {
-            ok = true;
-            *used_cards_mask = self.board_mask;
-            for i in 0..self.combined_ranges.len() {
-                let combo_idx = combo_dists[i].sample(rng);
-                combo_indexes[i] = combo_idx;
-                let combo = &self.combined_ranges[i].combos()[combo_idx];
-                if (*used_cards_mask & combo.mask) != 0 {
-                    ok = false;
-                    break;
-                }
-                for j in 0..self.combined_ranges[i].player_count() {
-                    let player_idx = self.combined_ranges[i].players()[j];
-                    player_hands[player_idx] = combo.hands[j];
-                    combo_weights[player_idx] = combo.hole_cards[j].2;
-                }
-                *used_cards_mask |= combo.mask;
-            }
-            if ok {
-                return true;
+        *used_cards_mask = match deal_combos(
+            &self.combined_ranges,
+            self.board_mask | self.dead_mask,
+            combo_indexes,
+            rng,
+            |i, rng| combo_dists[i].sample(rng),
+        ) {
+            Some(mask) => mask,
+            None => return false,
+        };
+        for (cr, combo_idx) in self.combined_ranges.iter().zip(combo_indexes.iter()) {
+            let combo = &cr.combos()[*combo_idx];
+            for j in 0..cr.player_count() {
+                let player_idx = cr.players()[j];
+                player_hands[player_idx] = combo.hands[j];
+                hole_cards[player_idx] = combo.hole_cards[j];
             }
         }
-        false
+        true
     }
 
     fn update_results(&self, batch: &SimulationResultsBatch, finished: bool) {
+        self.merge_results(batch, finished);
+        self.publish(false);
+    }
+
+    fn merge_results(&self, batch: &SimulationResultsBatch, finished: bool) {
         // get lock
         let mut results = self.results.write().unwrap();
-        let mut batch_hands = 0u64;
-        let mut batch_equity = 0f64;
-        for i in 0..(1 << self.n_players) {
-            let winner_count: u64 = u64::from((i as u32).count_ones());
-            batch_hands += batch.wins_by_mask[i];
+        let mut batch_hands = 0f64;
+        let mut batch_equity = [0f64; MAX_PLAYERS];
+        let results = &mut *results;
+        batch.wins_by_mask.for_each(|mask, w| {
+            let winner_count = mask.count_ones();
+            batch_hands += w;
             let mut actual_player_mask = 0;
             for j in 0..self.n_players {
-                if (i & (1 << j)) != 0 {
+                if (mask & (1 << j)) != 0 {
                     if winner_count == 1 {
-                        results.wins[batch.player_ids[j]] += batch.wins_by_mask[i];
-                        if batch.player_ids[j] == 0 {
-                            batch_equity += batch.wins_by_mask[i] as f64;
-                        }
+                        results.wins[batch.player_ids[j]] += w;
+                        batch_equity[batch.player_ids[j]] += w;
                     } else {
-                        results.ties[batch.player_ids[j]] +=
-                            (batch.wins_by_mask[i] / winner_count) as f64;
-                        if batch.player_ids[j] == 0 {
-                            batch_equity += (batch.wins_by_mask[i] / winner_count) as f64;
-                        }
+                        let tie_share = w / f64::from(winner_count);
+                        results.ties[batch.player_ids[j]] += tie_share;
+                        batch_equity[batch.player_ids[j]] += tie_share;
                     }
                     actual_player_mask |= 1 << batch.player_ids[j];
                 }
             }
-            results.wins_by_mask[actual_player_mask] += batch.wins_by_mask[i];
-        }
-        batch_equity /= (batch_hands as f64) + 1e-9;
-
+            results.wins_by_mask.add(actual_player_mask, w);
+        });
         results.eval_count += batch.eval_count;
         if !self.calc_exact {
-            results.batch_sum += batch_equity;
-            results.batch_sum2 += batch_equity * batch_equity;
             results.batch_count += 1.0;
-            results.stdev = (1e-9 + results.batch_sum2
-                - results.batch_sum * results.batch_sum / results.batch_count)
-                .sqrt()
-                / results.batch_count;
+            for (i, e) in batch_equity[0..self.n_players].iter().enumerate() {
+                let equity = e / (batch_hands + 1e-9);
+                results.batch_sum[i] += equity;
+                results.batch_sum2[i] += equity * equity;
+                results.stdev[i] = (1e-9 + results.batch_sum2[i]
+                    - results.batch_sum[i] * results.batch_sum[i] / results.batch_count)
+                    .sqrt()
+                    / results.batch_count;
+            }
 
-            // calc variance
-            if !finished && results.stdev < self.stdev_target {
-                self.stopped.store(true);
+            if !finished {
+                self.check_stopping_rules(results.batch_count, results.eval_count, &results.stdev);
             }
         }
     }
 
+    /// Stop the monte carlo simulation if a target or budget was reached
+    fn check_stopping_rules(&self, batch_count: f64, eval_count: u64, stdev: &[f64]) {
+        if let Some(reason) = self.stopping_rules.reached(batch_count, eval_count, stdev) {
+            self.stop(reason);
+        }
+    }
+
     fn evaluate_hands(
         &self,
         player_hands: &[Hand],
-        weight: u64,
+        weight: f64,
         board: &Hand,
         results: &mut SimulationResultsBatch,
         flush_possible: bool,
-    ) {
+    ) -> usize {
         // evaulate hands
-        let mut winner_mask: u8 = 0;
+        let mut winner_mask: u32 = 0;
         let mut best_score: u16 = 0;
-        let mut player_mask: u8 = 1;
+        let mut player_mask: u32 = 1;
         for i in 0..self.n_players {
+            if (self.contenders >> results.player_ids[i]) & 1 == 0 {
+                player_mask <<= 1;
+                continue;
+            }
             let hand: Hand = *board + player_hands[i];
-            let score = if flush_possible {
+            let score = if self.short_deck {
+                evaluate_short_deck(&hand)
+            } else if flush_possible {
                 evaluate(&hand)
             } else {
                 evaluate_without_flush(&hand)
@@ -912,18 +2368,78 @@ impl Simulator {
             }
             player_mask <<= 1;
         }
-        results.wins_by_mask[usize::from(winner_mask)] += weight;
+        results.wins_by_mask.add(winner_mask, weight);
         results.eval_count += 1;
+        winner_mask as usize
     }
 }
 
+/// Draw one combo of each combined range with no two sharing a card
+///
+/// `draw` picks a combo index of the i-th combined range.
+/// Full deals are drawn first, a full deal rarely fits with many players,
+/// so after that one combined range is dealt at a time.
+/// Returns the used cards mask, or `None` if no deal was found
+///
+/// # Arguments
+///
+/// * `combined_ranges` Ranges to deal from
+/// * `start_mask` Cards that can't be dealt
+/// * `combo_indexes` Receives the combo index of each combined range
+/// * `rng` Random number generator
+/// * `draw` Picks a combo index of a combined range
+pub(super) fn deal_combos<R: Rng, F: FnMut(usize, &mut R) -> usize>(
+    combined_ranges: &[CombinedRange],
+    start_mask: u64,
+    combo_indexes: &mut [usize],
+    rng: &mut R,
+    mut draw: F,
+) -> Option<u64> {
+    'full: for _ in 0..MAX_DEAL_ATTEMPTS {
+        let mut used_cards_mask = start_mask;
+        for (i, cr) in combined_ranges.iter().enumerate() {
+            let combo_idx = draw(i, rng);
+            let combo = &cr.combos()[combo_idx];
+            if (used_cards_mask & combo.mask) != 0 {
+                continue 'full;
+            }
+            combo_indexes[i] = combo_idx;
+            used_cards_mask |= combo.mask;
+        }
+        return Some(used_cards_mask);
+    }
+    'partial: for _ in 0..MAX_PARTIAL_DEAL_ATTEMPTS {
+        let mut used_cards_mask = start_mask;
+        for (i, cr) in combined_ranges.iter().enumerate() {
+            let dealt = (0..MAX_DEAL_ATTEMPTS).find_map(|_| {
+                let combo_idx = draw(i, rng);
+                let combo = &cr.combos()[combo_idx];
+                if (used_cards_mask & combo.mask) != 0 {
+                    None
+                } else {
+                    Some((combo_idx, combo.mask))
+                }
+            });
+            match dealt {
+                Some((combo_idx, mask)) => {
+                    combo_indexes[i] = combo_idx;
+                    used_cards_mask |= mask;
+                }
+                None => continue 'partial,
+            }
+        }
+        return Some(used_cards_mask);
+    }
+    None
+}
+
 fn randomize_board<R: Rng>(
     rng: &mut R,
     board: &mut Hand,
     mut used_cards_mask: u64,
     cards_remaining: u32,
     card_dist: &Uniform<u8>,
-) {
+) -> u64 {
     // randomize board
     for _ in 0..cards_remaining {
         let mut card: u8;
@@ -938,6 +2454,64 @@ fn randomize_board<R: Rng>(
         used_cards_mask |= card_mask;
         *board += CARDS[usize::from(card)];
     }
+    used_cards_mask
+}
+
+/// Complete the board with cards picked by uniform numbers
+///
+/// Each number picks from the cards left in the deck in index order,
+/// so close numbers pick close cards
+fn deal_board_from_uniforms(
+    mut board: Hand,
+    used_cards_mask: u64,
+    first_card: u8,
+    uniforms: &[f64],
+) -> Hand {
+    let mut free = !used_cards_mask & (FULL_DECK_MASK << first_card) & FULL_DECK_MASK;
+    for u in uniforms {
+        let n_free = free.count_ones();
+        let idx = ((u * f64::from(n_free)) as u32).min(n_free - 1);
+        let card = nth_set_bit(free, idx);
+        board += CARDS[card as usize];
+        free &= !(1u64 << card);
+    }
+    board
+}
+
+/// Index of the `n`th lowest set bit of a mask
+fn nth_set_bit(mut mask: u64, mut n: u32) -> u32 {
+    let mut offset = 0;
+    // skip whole bytes first
+    loop {
+        let count = (mask & 0xff).count_ones();
+        if n < count {
+            break;
+        }
+        n -= count;
+        mask >>= 8;
+        offset += 8;
+    }
+    for _ in 0..n {
+        mask &= mask - 1;
+    }
+    offset + mask.trailing_zeros()
+}
+
+/// Call `f` with the mask of every `count` card subset of `deck[start..]`
+pub(super) fn for_each_card_subset<F: FnMut(u64)>(
+    deck: &[u8],
+    count: u32,
+    start: usize,
+    mask: u64,
+    f: &mut F,
+) {
+    if count == 0 {
+        f(mask);
+        return;
+    }
+    for (i, card) in deck.iter().enumerate().skip(start) {
+        for_each_card_subset(deck, count - 1, i + 1, mask | (1u64 << card), f);
+    }
 }
 
 #[cfg(test)]
@@ -967,6 +2541,97 @@ mod tests {
         assert_eq!(equity[0], 0.8130232455484216);
     }
 
+    #[test]
+    fn test_exact_fractional_weighted() {
+        const THREADS: u8 = 8;
+        let ranges =
+            HandRange::from_strings(["KK".to_string(), "AA@0.5,QQ@50".to_string()].to_vec());
+        let board_mask = get_card_mask("");
+        let equity = exact_equity(&ranges, board_mask, THREADS).unwrap();
+        println!("{:?}", equity);
+        assert!((equity[0] - 0.8130232455484216).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_exact_combo_equity() {
+        let ranges =
+            HandRange::from_strings(["QQ+,AKs".to_string(), "JJ,AK@50".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Kh7c2d"),
+            n_threads: 4,
+            combo_equity: true,
+            ..EquityOptions::default()
+        };
+        let result = exact_equity_with_options(&ranges, &options).unwrap();
+        let combo_equities = result.combo_equities.unwrap();
+        for (player, combos) in combo_equities.iter().enumerate() {
+            let weight: f64 = combos.iter().map(|c| c.weight).sum();
+            let equity: f64 = combos.iter().map(|c| c.weight * c.equity).sum();
+            assert!((weight - 1.0).abs() < 1e-9);
+            assert!((equity - result.equities[player]).abs() < 1e-9);
+        }
+        // a single combo has the same equity as a range of only that combo
+        let aces = combo_equities[0]
+            .iter()
+            .find(|c| c.combo.hand_class() == "AA")
+            .unwrap();
+        let hand = format!("{}", aces.combo);
+        let single = HandRange::from_strings([hand, "JJ,AK@50".to_string()].to_vec());
+        let equity = exact_equity(&single, options.board_mask, 4).unwrap();
+        assert!((aces.equity - equity[0]).abs() < 1e-9);
+        assert!(aces.win + aces.tie <= 1.0);
+    }
+
+    #[test]
+    fn test_exact_combo_equity_multiway() {
+        let ranges = HandRange::from_strings(
+            ["AK".to_string(), "KQs,QQ".to_string(), "JJ+".to_string()].to_vec(),
+        );
+        let board_mask = get_card_mask("Ks9h5c2d");
+        let options = EquityOptions {
+            board_mask,
+            n_threads: 4,
+            combo_equity: true,
+            ..EquityOptions::default()
+        };
+        let result = exact_equity_with_options(&ranges, &options).unwrap();
+        let combo_equities = result.combo_equities.unwrap();
+        for (player, combos) in combo_equities.iter().enumerate() {
+            let equity: f64 = combos.iter().map(|c| c.weight * c.equity).sum();
+            assert!((equity - result.equities[player]).abs() < 1e-9);
+            for c in combos {
+                let mut single = ranges.clone();
+                single[player] = HandRange::from_string(format!("{}", c.combo));
+                let equity = exact_equity(&single, board_mask, 4).unwrap();
+                assert!((c.equity - equity[player]).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_approx_combo_equity() {
+        let ranges = HandRange::from_strings(["AA,72o".to_string(), "KK".to_string()].to_vec());
+        let options = EquityOptions {
+            combo_equity: true,
+            ..EquityOptions::default()
+        };
+        let result = approx_equity_with_options(&ranges, &options).unwrap();
+        let combo_equities = result.combo_equities.unwrap();
+        for c in &combo_equities[0] {
+            if c.combo.hand_class() == "AA" {
+                assert!(c.equity > 0.75 && c.equity < 0.9);
+            } else {
+                assert!(c.equity < 0.2);
+            }
+        }
+        assert!(
+            exact_equity_with_options(&ranges, &EquityOptions::default())
+                .unwrap()
+                .combo_equities
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_preflop_accuracy() {
         const THREADS: u8 = 8;
@@ -977,6 +2642,403 @@ mod tests {
         assert_eq!(equity[0], 0.8520371330210104);
     }
 
+    #[test]
+    fn test_exact_result_stats() {
+        let ranges = HandRange::from_strings(["AsKd".to_string(), "AhKc,QQ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c3d4h5sQd"),
+            ..EquityOptions::default()
+        };
+        let result = exact_equity_with_options(&ranges, &options).unwrap();
+        assert!(result.exact);
+        assert_eq!(result.stdev, vec![0.0, 0.0]);
+        // the wheel splits against AK and beats the three sets of queens
+        assert!((result.wins[0] - 0.75).abs() < 1e-9);
+        assert_eq!(result.wins[1], 0.0);
+        assert!((result.ties[0] - 0.25).abs() < 1e-9);
+        assert!((result.wins_by_mask[&0b11] - 0.25).abs() < 1e-9);
+        assert!((result.equities[0] - 0.875).abs() < 1e-9);
+        assert_eq!(result.eval_count, 4);
+    }
+
+    #[test]
+    fn test_approx_result_stats() {
+        let ranges = HandRange::from_strings(
+            ["QQ".to_string(), "AKs".to_string(), "random".to_string()].to_vec(),
+        );
+        let options = EquityOptions {
+            board_mask: get_card_mask("Jh8c3d"),
+            ..EquityOptions::default()
+        };
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        let result = approx_equity_with_options(&ranges, &options).unwrap();
+        assert!(!result.exact);
+        assert!(result.eval_count > 0);
+        let total: f64 = result.wins_by_mask.values().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        for i in 0..3 {
+            assert!(result.stdev[i] > 0.0);
+            let (low, high) = result.confidence_interval(i, 5.0);
+            assert!(low < exact.equities[i] && exact.equities[i] < high);
+        }
+    }
+
+    /// Brute force heads up equity of one hand vs a range with two cards to come
+    fn brute_force_equity(
+        hero: &str,
+        villain: &HandRange,
+        board: &str,
+        dead_mask: u64,
+        evaluator: fn(&Hand) -> u16,
+    ) -> f64 {
+        let board_mask = get_card_mask(board);
+        let hero_mask = get_card_mask(hero);
+        let hero_hand = Hand::from_hole_cards(
+            63 - hero_mask.leading_zeros() as u8,
+            hero_mask.trailing_zeros() as u8,
+        );
+        let used = board_mask | hero_mask | dead_mask;
+        let mut equity = 0.0;
+        let mut count = 0.0;
+        for c in &villain.hands {
+            let villain_mask = (1u64 << c.0) | (1u64 << c.1);
+            if (villain_mask & used) != 0 {
+                continue;
+            }
+            for t in 0..52u8 {
+                for r in 0..t {
+                    let runout = (1u64 << t) | (1u64 << r);
+                    if (runout & (used | villain_mask)) != 0 {
+                        continue;
+                    }
+                    let full_board = Hand::from_bit_mask(board_mask | runout);
+                    let h = evaluator(&(full_board + hero_hand));
+                    let v = evaluator(&(full_board + Hand::from_hole_cards(c.0, c.1)));
+                    equity += match h.cmp(&v) {
+                        Ordering::Greater => 1.0,
+                        Ordering::Equal => 0.5,
+                        Ordering::Less => 0.0,
+                    };
+                    count += 1.0;
+                }
+            }
+        }
+        equity / count
+    }
+
+    #[test]
+    fn test_short_deck() {
+        let ranges =
+            HandRange::from_strings(["AhKh".to_string(), "QQ,JTs,A9o,22+".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Qd8h6c"),
+            short_deck: true,
+            seed: Some(5),
+            ..EquityOptions::default()
+        };
+        let expected = brute_force_equity(
+            "AhKh",
+            &ranges[1],
+            "Qd8h6c",
+            SHORT_DECK_REMOVED_CARDS,
+            evaluate_short_deck,
+        );
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        assert!((exact.equities[0] - expected).abs() < 1e-9);
+        let approx = approx_equity_with_options(&ranges, &options).unwrap();
+        assert!((approx.equities[0] - expected).abs() < 0.01);
+        let full_deck = exact_equity(&ranges, options.board_mask, 4).unwrap();
+        assert!((full_deck[0] - exact.equities[0]).abs() > 0.01);
+
+        // preflop runs through the lookup table
+        let ranges = HandRange::from_strings(["AKs".to_string(), "TT,99".to_string()].to_vec());
+        let options = EquityOptions {
+            short_deck: true,
+            seed: Some(5),
+            ..EquityOptions::default()
+        };
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        let approx = approx_equity_with_options(&ranges, &options).unwrap();
+        assert!((approx.equities[0] - exact.equities[0]).abs() < 0.01);
+
+        // deuces to fives are not in the deck
+        let ranges = HandRange::from_strings(["55".to_string(), "AA".to_string()].to_vec());
+        assert!(matches!(
+            exact_equity_with_options(
+                &ranges,
+                &EquityOptions {
+                    short_deck: true,
+                    ..EquityOptions::default()
+                }
+            ),
+            Err(SimulatorError::ConflictingRanges)
+        ));
+        let ranges = HandRange::from_strings(["AA".to_string(), "KK".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2c7d8h"),
+            short_deck: true,
+            ..EquityOptions::default()
+        };
+        assert!(matches!(
+            exact_equity_with_options(&ranges, &options),
+            Err(SimulatorError::CardNotInDeck)
+        ));
+    }
+
+    #[test]
+    fn test_dead_cards() {
+        let ranges = HandRange::from_strings(["AsKs".to_string(), "QQ,JJ".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Qs7s2d"),
+            dead_mask: get_card_mask("3s4sJc"),
+            ..EquityOptions::default()
+        };
+        let expected = brute_force_equity(
+            "AsKs",
+            &ranges[1],
+            "Qs7s2d",
+            get_card_mask("3s4sJc"),
+            evaluate,
+        );
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        assert!((exact.equities[0] - expected).abs() < 1e-9);
+        // dead spades remove flush outs
+        let live = exact_equity(&ranges, options.board_mask, 4).unwrap();
+        assert!(live[0] > exact.equities[0]);
+        let approx = approx_equity_with_options(&ranges, &options).unwrap();
+        assert!((approx.equities[0] - expected).abs() < 0.01);
+        // too many dead cards to deal the board
+        let options = EquityOptions {
+            dead_mask: !get_card_mask("2c2d3c3d4c4d5c5d"),
+            ..EquityOptions::default()
+        };
+        let ranges = HandRange::from_strings(["22".to_string(), "33".to_string()].to_vec());
+        assert!(exact_equity_with_options(&ranges, &options).is_err());
+    }
+
+    #[test]
+    fn test_full_ring() {
+        // nine players on the flop, enumerated without the lookup table
+        let hands = [
+            "AsAh", "KsKh", "QsQh", "JsJh", "Ts9s", "8d7d", "6c5c", "Ac2d", "KdQc",
+        ];
+        let ranges = HandRange::from_strings(hands.iter().map(|h| h.to_string()).collect());
+        let options = EquityOptions {
+            board_mask: get_card_mask("2s3s4h"),
+            ..EquityOptions::default()
+        };
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        let approx = approx_equity_with_options(&ranges, &options).unwrap();
+        let total: f64 = exact.equities.iter().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        for i in 0..hands.len() {
+            assert!((exact.equities[i] - approx.equities[i]).abs() < 0.02);
+        }
+        assert!(exact
+            .wins_by_mask
+            .keys()
+            .all(|mask| *mask < 1 << hands.len()));
+    }
+
+    #[test]
+    fn test_max_players() {
+        let ranges = vec![HandRange::from_string("random".to_string()); MAX_PLAYERS];
+        let options = EquityOptions {
+            stdev_target: 0.01,
+            ..EquityOptions::default()
+        };
+        let result = approx_equity_with_options(&ranges, &options).unwrap();
+        for e in &result.equities {
+            assert!((e - 1.0 / MAX_PLAYERS as f64).abs() < 0.03);
+        }
+        assert!(exact_equity_with_options(&ranges, &options).is_err());
+        let ranges = vec![HandRange::from_string("random".to_string()); MAX_PLAYERS + 1];
+        assert!(approx_equity_with_options(&ranges, &options).is_err());
+    }
+
+    #[test]
+    fn test_seeded_approx() {
+        let ranges = HandRange::from_strings(
+            [
+                "QQ+,AK".to_string(),
+                "random".to_string(),
+                "22+".to_string(),
+            ]
+            .to_vec(),
+        );
+        let run = |n_threads, seed| {
+            let options = EquityOptions {
+                n_threads,
+                seed: Some(seed),
+                combo_equity: true,
+                stdev_target: 0.002,
+                ..EquityOptions::default()
+            };
+            approx_equity_with_options(&ranges, &options).unwrap()
+        };
+        let a = run(1, 42);
+        for n_threads in [2, 4, 8].iter() {
+            let b = run(*n_threads, 42);
+            assert_eq!(a.equities, b.equities);
+            assert_eq!(a.eval_count, b.eval_count);
+            assert_eq!(a.stdev, b.stdev);
+            let a_combos = a.combo_equities.as_ref().unwrap();
+            let b_combos = b.combo_equities.as_ref().unwrap();
+            for (x, y) in a_combos[0].iter().zip(b_combos[0].iter()) {
+                assert_eq!(x.equity, y.equity);
+            }
+        }
+        assert_ne!(a.equities, run(4, 43).equities);
+    }
+
+    #[test]
+    fn test_stopping_rules() {
+        let ranges = HandRange::from_strings(["AK".to_string(), "22+".to_string()].to_vec());
+        let base = EquityOptions {
+            seed: Some(7),
+            stdev_target: 0.0,
+            ..EquityOptions::default()
+        };
+        let run = |options: EquityOptions| approx_equity_with_options(&ranges, &options).unwrap();
+
+        let result = run(EquityOptions {
+            max_batches: Some(20),
+            ..base.clone()
+        });
+        assert_eq!(result.stop_reason, StopReason::MaxBatches);
+        assert_eq!(result.eval_count, 20 * MONTE_CARLO_BATCH_SIZE);
+
+        let result = run(EquityOptions {
+            max_evaluations: Some(100_000),
+            ..base.clone()
+        });
+        assert_eq!(result.stop_reason, StopReason::MaxEvaluations);
+        assert!(result.eval_count >= 100_000);
+        assert!(result.eval_count < 100_000 + MONTE_CARLO_BATCH_SIZE);
+
+        let result = run(EquityOptions {
+            confidence_target: Some(0.01),
+            ..base.clone()
+        });
+        assert_eq!(result.stop_reason, StopReason::ConfidenceTarget);
+        for i in 0..2 {
+            let (low, high) = result.confidence_interval(i, 1.96);
+            assert!(high - low < 0.02);
+        }
+
+        let time_limit = Duration::from_millis(50);
+        let start = Instant::now();
+        let result = run(EquityOptions {
+            time_limit: Some(time_limit),
+            ..base.clone()
+        });
+        assert_eq!(result.stop_reason, StopReason::TimeLimit);
+        assert!(start.elapsed() < time_limit * 4);
+        assert!(result.eval_count > 0);
+
+        let result = run(EquityOptions {
+            stdev_target: 0.002,
+            max_batches: Some(1_000_000),
+            ..base.clone()
+        });
+        assert_eq!(result.stop_reason, StopReason::StdevTarget);
+    }
+
+    #[test]
+    fn test_variance_reduction_is_unbiased() {
+        let ranges =
+            HandRange::from_strings(["AK@30,TT".to_string(), "QQ+,98s".to_string()].to_vec());
+        let base = EquityOptions {
+            board_mask: get_card_mask("Ks8d2c"),
+            stdev_target: 0.002,
+            seed: Some(9),
+            ..EquityOptions::default()
+        };
+        let exact = exact_equity_with_options(&ranges, &base).unwrap();
+        for board_sampling in [
+            BoardSampling::Random,
+            BoardSampling::Stratified,
+            BoardSampling::Antithetic,
+            BoardSampling::QuasiRandom,
+        ] {
+            for stratify_combos in [false, true] {
+                let result = approx_equity_with_options(
+                    &ranges,
+                    &EquityOptions {
+                        board_sampling,
+                        stratify_combos,
+                        ..base.clone()
+                    },
+                )
+                .unwrap();
+                let error = (result.equities[0] - exact.equities[0]).abs();
+                assert!(
+                    error < 0.01,
+                    "{:?} {} {}",
+                    board_sampling,
+                    stratify_combos,
+                    error
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_stratified_needs_fewer_evaluations() {
+        // on the turn the only card left is stratified
+        let ranges = HandRange::from_strings(["AhKh".to_string(), "QsQd".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Qh7h2c3d"),
+            stdev_target: 0.001,
+            seed: Some(2),
+            ..EquityOptions::default()
+        };
+        let random = approx_equity_with_options(&ranges, &options).unwrap();
+        let stratified = approx_equity_with_options(
+            &ranges,
+            &EquityOptions {
+                board_sampling: BoardSampling::Stratified,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert!(2 * stratified.eval_count < random.eval_count);
+        let exact = exact_equity_with_options(&ranges, &options).unwrap();
+        assert!((stratified.equities[0] - exact.equities[0]).abs() < 0.003);
+    }
+
+    #[bench]
+    fn bench_sampling_random_flop(b: &mut Bencher) {
+        let ranges = HandRange::from_strings(["AK,TT".to_string(), "QQ+,98s".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Ks8d2c"),
+            ..EquityOptions::default()
+        };
+        b.iter(|| approx_equity_with_options(&ranges, &options).unwrap());
+    }
+
+    #[bench]
+    fn bench_sampling_stratified_flop(b: &mut Bencher) {
+        let ranges = HandRange::from_strings(["AK,TT".to_string(), "QQ+,98s".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Ks8d2c"),
+            board_sampling: BoardSampling::Stratified,
+            ..EquityOptions::default()
+        };
+        b.iter(|| approx_equity_with_options(&ranges, &options).unwrap());
+    }
+
+    #[bench]
+    fn bench_sampling_quasi_random_flop(b: &mut Bencher) {
+        let ranges = HandRange::from_strings(["AK,TT".to_string(), "QQ+,98s".to_string()].to_vec());
+        let options = EquityOptions {
+            board_mask: get_card_mask("Ks8d2c"),
+            board_sampling: BoardSampling::QuasiRandom,
+            ..EquityOptions::default()
+        };
+        b.iter(|| approx_equity_with_options(&ranges, &options).unwrap());
+    }
+
     #[bench]
     fn bench_random_random(b: &mut Bencher) {
         // best score with these params
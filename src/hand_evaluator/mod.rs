@@ -1,5 +1,7 @@
 mod evaluator;
 mod hand;
+mod short_deck;
 
 pub use evaluator::*;
 pub use hand::{Hand, CARDS};
+pub use short_deck::evaluate_short_deck;
@@ -0,0 +1,135 @@
+use super::{evaluate, Hand, CARDS};
+use crate::constants::*;
+
+/// Rank bits of A6789, the lowest straight in a short deck
+const SHORT_WHEEL_RANKS: u64 = 0x10f0;
+/// Rank bits of one suit in a hand mask
+const SUIT_RANKS: u64 = 0x1fff;
+
+lazy_static! {
+    /// Score of A2345, the lowest full deck straight
+    static ref LOWEST_STRAIGHT: u16 = evaluate(&hand_from_cards(&[48, 1, 5, 9, 13]));
+    /// Score of A2345 suited, the lowest full deck straight flush
+    static ref LOWEST_STRAIGHT_FLUSH: u16 = evaluate(&hand_from_cards(&[48, 0, 4, 8, 12]));
+}
+
+fn hand_from_cards(cards: &[u8]) -> Hand {
+    cards
+        .iter()
+        .fold(Hand::default(), |hand, c| hand + CARDS[usize::from(*c)])
+}
+
+/// Move a full deck score into the short deck category order,
+/// where three of a kind beats a straight and a flush beats a full house
+fn to_short_deck_order(score: u16) -> u16 {
+    let category = score & !(HAND_CATEGORY_OFFSET - 1);
+    let category = match category {
+        THREE_OF_A_KIND => STRAIGHT,
+        STRAIGHT => THREE_OF_A_KIND,
+        FLUSH => FULL_HOUSE,
+        FULL_HOUSE => FLUSH,
+        c => c,
+    };
+    category | (score & (HAND_CATEGORY_OFFSET - 1))
+}
+
+/// Evaluates a single short deck hand and returns score
+///
+/// The deck has 36 cards, sixes to aces. A6789 is the lowest straight,
+/// three of a kind beats a straight and a flush beats a full house.
+/// Scores can only be compared with other short deck scores.
+///
+/// # Example
+/// ```
+/// use rust_poker::hand_evaluator::{evaluate_short_deck, Hand};
+/// use rust_poker::hand_range::get_card_mask;
+/// let flush = evaluate_short_deck(&Hand::from_bit_mask(get_card_mask("AhJh9h7h6h")));
+/// let full_house = evaluate_short_deck(&Hand::from_bit_mask(get_card_mask("KsKdKc6s6d")));
+/// assert!(flush > full_house);
+/// ```
+pub fn evaluate_short_deck(hand: &Hand) -> u16 {
+    let score = evaluate(hand);
+    let mut best = to_short_deck_order(score);
+    let mask = hand.get_mask();
+    let mut rank_counts = [0u8; 13];
+    let mut ranks = 0u64;
+    for suit in 0..u64::from(SUIT_COUNT) {
+        let suit_ranks = (mask >> (16 * suit)) & SUIT_RANKS;
+        if (suit_ranks & SHORT_WHEEL_RANKS) == SHORT_WHEEL_RANKS {
+            best = best.max(to_short_deck_order(*LOWEST_STRAIGHT_FLUSH));
+        }
+        ranks |= suit_ranks;
+        for (rank, count) in rank_counts.iter_mut().enumerate() {
+            *count += ((suit_ranks >> rank) & 1) as u8;
+        }
+    }
+    if (ranks & SHORT_WHEEL_RANKS) == SHORT_WHEEL_RANKS {
+        best = best.max(to_short_deck_order(*LOWEST_STRAIGHT));
+    }
+    // a straight hides three of a kind, which ranks higher in a short deck
+    if (score & !(HAND_CATEGORY_OFFSET - 1)) == STRAIGHT {
+        if let Some(trips) = rank_counts.iter().rposition(|c| *c >= 3) {
+            let mut kickers = (0..13u8)
+                .rev()
+                .filter(|r| usize::from(*r) != trips && rank_counts[usize::from(*r)] > 0);
+            let trips = trips as u8;
+            let k1 = kickers.next().unwrap();
+            let k2 = kickers.next().unwrap();
+            let trips_hand =
+                hand_from_cards(&[4 * trips, 4 * trips + 1, 4 * trips + 2, 4 * k1, 4 * k2 + 1]);
+            best = best.max(to_short_deck_order(evaluate(&trips_hand)));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    fn score(text: &str) -> u16 {
+        evaluate_short_deck(&Hand::from_bit_mask(get_card_mask(text)))
+    }
+
+    #[test]
+    fn test_category_order() {
+        let straight_flush = score("Ts9s8s7s6s");
+        let quads = score("AsAhAdAcKs");
+        let flush = score("AhJh9h7h6h");
+        let full_house = score("KsKdKc6s6d");
+        let trips = score("7s7h7dAsKd");
+        let straight = score("AsKhQdJcTs");
+        let two_pair = score("AsAhKdKcQs");
+        assert!(straight_flush > quads);
+        assert!(quads > flush);
+        assert!(flush > full_house);
+        assert!(full_house > trips);
+        assert!(trips > straight);
+        assert!(straight > two_pair);
+    }
+
+    #[test]
+    fn test_short_wheel() {
+        let wheel = score("As6h7d8c9s");
+        assert!(wheel > score("AsAhKdKcQs"));
+        assert!(wheel < score("6s7h8d9cTs"));
+        assert!(wheel < score("6s6h6dAcKs"));
+        // 7 card hands still find the wheel
+        assert_eq!(score("As6h7d8c9sJsQh"), wheel);
+        let wheel_flush = score("As6s7s8s9s");
+        assert!(wheel_flush > score("AsAhAdAcKs"));
+        assert!(wheel_flush < score("6s7s8s9sTs"));
+        assert_eq!(score("As6s7s8s9sAhAd"), wheel_flush);
+    }
+
+    #[test]
+    fn test_trips_beat_straight_in_seven_cards() {
+        // 999 with 6789T plays as trips
+        let hand = score("9s9h9d6c7s8hTd");
+        assert_eq!(hand, score("9s9h9dTd8h"));
+        assert!(hand > score("6c7s8h9dTd"));
+        // trips with the short wheel also play as trips
+        assert_eq!(score("As6h7d8c9s9h9d"), score("9s9h9dAs8c"));
+    }
+}
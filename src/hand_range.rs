@@ -6,17 +6,72 @@
  */
 
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
 use std::iter::FromIterator;
+use std::str::FromStr;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::constants::*;
 
+#[derive(Debug, Error)]
+pub enum HandRangeError {
+    #[error("invalid hand: {0}")]
+    InvalidHand(String),
+}
+
 /// A single player hand
 /// 0: index of card 1
 /// 1: index of card 2
-/// 2: weight of combo
-#[derive(Debug, Clone, Copy)]
-pub struct Combo(pub u8, pub u8, pub u8);
+/// 2: weight of combo (0-100)
+///
+/// Serialized as `{"hand": "AhKd", "weight": 100.0}`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "ComboRepr", into = "ComboRepr")]
+pub struct Combo(pub u8, pub u8, pub f32);
+
+/// Serde representation of a combo
+#[derive(Serialize, Deserialize)]
+struct ComboRepr {
+    hand: String,
+    #[serde(default = "default_weight")]
+    weight: f32,
+}
+
+fn default_weight() -> f32 {
+    100.0
+}
+
+impl From<Combo> for ComboRepr {
+    fn from(combo: Combo) -> Self {
+        ComboRepr {
+            hand: combo.to_string(),
+            weight: combo.2,
+        }
+    }
+}
+
+impl TryFrom<ComboRepr> for Combo {
+    type Error = String;
+
+    fn try_from(repr: ComboRepr) -> Result<Self, Self::Error> {
+        let mask = get_card_mask(&repr.hand);
+        if mask.count_ones() != 2 || repr.hand.len() != 4 {
+            return Err(format!("invalid combo: {}", repr.hand));
+        }
+        if !(0.0..=100.0).contains(&repr.weight) {
+            return Err(format!("invalid weight: {}", repr.weight));
+        }
+        // highest card first, same as ranges parsed from strings
+        let c1 = 63 - mask.leading_zeros() as u8;
+        let c2 = mask.trailing_zeros() as u8;
+        Ok(Combo(c1, c2, repr.weight))
+    }
+}
 
 impl fmt::Display for Combo {
     /// Writes hole cards to string
@@ -25,7 +80,7 @@ impl fmt::Display for Combo {
     /// ```
     /// // prints '2s2h'
     /// use rust_poker::hand_range::Combo;
-    /// let hand = Combo(0, 1, 100);
+    /// let hand = Combo(0, 1, 100.0);
     /// println!("{}", hand.to_string());
     /// ```
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -39,6 +94,36 @@ impl fmt::Display for Combo {
     }
 }
 
+impl Combo {
+    /// Get the preflop hand class of the combo, such as "AA", "AKs" or "AKo"
+    ///
+    /// # Example
+    /// ```
+    /// use rust_poker::hand_range::Combo;
+    /// let hand = Combo(51, 47, 100.0);
+    /// assert_eq!(hand.hand_class(), "AKs");
+    /// ```
+    pub fn hand_class(&self) -> String {
+        let (high, low) = if self.0 >> 2 >= self.1 >> 2 {
+            (self.0, self.1)
+        } else {
+            (self.1, self.0)
+        };
+        let mut class = String::new();
+        class.push(RANK_TO_CHAR[usize::from(high >> 2)]);
+        class.push(RANK_TO_CHAR[usize::from(low >> 2)]);
+        if (high >> 2) != (low >> 2) {
+            class.push(if (high & 3) == (low & 3) { 's' } else { 'o' });
+        }
+        class
+    }
+
+    /// Get the 64 bit card mask of the combo
+    pub const fn mask(&self) -> u64 {
+        (1u64 << self.0) | (1u64 << self.1)
+    }
+}
+
 impl Ord for Combo {
     fn cmp(&self, other: &Self) -> Ordering {
         if (self.0 >> 2) != (other.0 >> 2) {
@@ -75,26 +160,25 @@ impl PartialEq for Combo {
 impl Eq for Combo {}
 
 /// A range of private player hands for texas holdem
+///
+/// Serialized as an equilab-like range string, e.g. "AA,AKs@50".
+/// Can be deserialized from a range string or a list of combos
 #[derive(Debug, Clone)]
 pub struct HandRange {
     /// A vector of possible hole card combinations
     pub hands: Vec<Combo>,
-    pub char_vec: Vec<char>
 }
 
 impl HandRange {
     /// Creates an empty range of hands
     fn new() -> Self {
-        HandRange {
-            hands: Vec::new(),
-            char_vec: Vec::new()
-        }
+        HandRange { hands: Vec::new() }
     }
 
     /// Create a vector of Handrange from a vector of strings
     ///
-    /// Supports weighting between 0-100 using the @0-100 after the combo.  If no weight is
-    /// specified, weights will default to 100.
+    /// Supports weighting between 0-100 using the @0-100 after the combo.  Weights may be
+    /// fractional (e.g. "AKs@37.25").  If no weight is specified, weights will default to 100.
     ///
     /// # Arguments
     ///
@@ -104,7 +188,7 @@ impl HandRange {
     ///
     /// ```
     /// use rust_poker::hand_range::HandRange;
-    /// let ranges = HandRange::from_strings(["22+,QQ@50".to_string(), "AKs".to_string()].to_vec());
+    /// let ranges = HandRange::from_strings(["22+,QQ@50".to_string(), "AKs@37.25".to_string()].to_vec());
     /// ```
     pub fn from_strings(arr: Vec<String>) -> Vec<Self> {
         arr.iter()
@@ -112,14 +196,76 @@ impl HandRange {
             .collect()
     }
 
+    /// Create a Handrange from a list of combos
+    ///
+    /// Duplicate combos are removed, keeping the first occurrence
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::{Combo, HandRange};
+    /// let range = HandRange::from_combos(vec![Combo(51, 47, 100.0), Combo(50, 46, 50.0)]);
+    /// ```
+    pub fn from_combos(combos: Vec<Combo>) -> Self {
+        let mut range = HandRange::new();
+        for c in combos {
+            range.add_combo(c.0, c.1, c.2);
+        }
+        range.remove_duplicates();
+        range
+    }
+
+    /// Create a Handrange from a single hand such as "AKs" or "AhKd"
+    ///
+    /// Returns `None` unless the whole string is one valid hand
+    pub(crate) fn from_hand_string(text: &str) -> Option<Self> {
+        let mut parser = RangeParser::new(text.trim());
+        let mut i: usize = 0;
+        if !parser.parse_hand(&mut i) || !parser.at_end(i) {
+            return None;
+        }
+        parser.range.remove_duplicates();
+        Some(parser.range)
+    }
+
     /// remove combos that conflict with board
     pub fn remove_conflicting_combos(&mut self, board_mask: u64) {
-        self.hands
-            .retain(|x| (((1u64 << x.0) | (1u64 << x.1)) & board_mask) == 0);
+        self.hands.retain(|x| (x.mask() & board_mask) == 0);
+    }
+
+    /// Draw one combo from the range in proportion to its weight
+    ///
+    /// Combos that conflict with dead cards are skipped.
+    /// Returns `None` if no combo with a positive weight is left
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Random number generator
+    /// * `dead_mask` - 64 bit mask of cards that can't be dealt
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::{HandRange, get_card_mask};
+    /// let range = HandRange::from_string("AA,KK@50".to_string());
+    /// let mut rng = rand::thread_rng();
+    /// let combo = range.sample(&mut rng, get_card_mask("AsAh")).unwrap();
+    /// ```
+    pub fn sample<R: Rng>(&self, rng: &mut R, dead_mask: u64) -> Option<Combo> {
+        let combos: Vec<&Combo> = self
+            .hands
+            .iter()
+            .filter(|c| (c.mask() & dead_mask) == 0)
+            .collect();
+        let dist = WeightedIndex::new(combos.iter().map(|c| c.2)).ok()?;
+        Some(*combos[dist.sample(rng)])
     }
 
     /// Create a Handrange from a string
     ///
+    /// Parsing stops at the first invalid hand, use `str::parse`
+    /// to reject ranges with invalid hands instead
+    ///
     /// # Arguments
     ///
     /// * `text` - A equilab-like range string
@@ -131,133 +277,22 @@ impl HandRange {
     /// let range = HandRange::from_string("JJ+".to_string());
     /// ```
     pub fn from_string(text: String) -> Self {
-        let mut range: HandRange = HandRange::new();
-
         if text == "random" {
+            let mut range = HandRange::new();
             range.add_all();
-        } else {
-            range.char_vec = text.to_lowercase().chars().collect();
-            range.char_vec.push(' ');
-            let mut i: usize = 0;
-            while range.parse_hand(&mut i) && range.parse_char(&mut i, ',') {}
-            range.remove_duplicates();
+            return range;
         }
-
-        range
-    }
-
-    fn parse_hand(&mut self, i: &mut usize) -> bool {
-        let backtrack = *i;
-
-        let explicit_suits: bool;
-        let mut weight: u8 = 100;
-        let mut r1: u8 = u8::MAX;
-        let mut r2: u8 = u8::MAX;
-        let mut s1: u8 = u8::MAX;
-        let mut s2: u8 = u8::MAX;
-
-        if !self.parse_rank(i, &mut r1) {
-            return false;
-        }
-        explicit_suits = self.parse_suit(i, &mut s1);
-        if !self.parse_rank(i, &mut r2) {
-            *i = backtrack;
-            return false;
-        }
-        if explicit_suits && !self.parse_suit(i, &mut s2) {
-            *i = backtrack;
-            return false;
-        }
-        if explicit_suits {
-            let c1 = 4 * r1 + s1;
-            let c2 = 4 * r2 + s2;
-            if c1 == c2 {
-                *i = backtrack;
-                return false;
-            }
-            if self.parse_char(i, '@') {
-                self.parse_weight(i, &mut weight);
-            }
-            self.add_combo(c1, c2, weight);
-        } else {
-            let mut suited = true;
-            let mut offsuited = true;
-            if self.parse_char(i, 'o') {
-                suited = false;
-            } else if self.parse_char(i, 's') {
-                offsuited = false;
-            }
-            if self.parse_char(i, '+') {
-                if self.parse_char(i, '@') {
-                    self.parse_weight(i, &mut weight);
-                }
-                self.add_combos_plus(r1, r2, suited, offsuited, weight);
-            } else {
-                if self.parse_char(i, '@') {
-                    self.parse_weight(i, &mut weight);
-                }
-                self.add_combos(r1, r2, suited, offsuited, weight);
-            }
-        }
-
-        true
-    }
-
-    fn parse_weight(&self, i: &mut usize, weight: &mut u8) -> bool {
-        let backtrack = *i;
-        let mut number = 0;
-        loop {
-            let digit = self.char_vec[*i].to_digit(10);
-            match digit {
-                Some(num) => {
-                    number *= 10;
-                    number += num;
-                    *i += 1;
-                }
-                None => {
-                    if number > 100 {
-                        *i = backtrack;
-                        return false;
-                    } else {
-                        *weight = number as u8;
-                        return true;
-                    }
-                }
-            }
-        }
-    }
-
-    fn parse_char(&mut self, i: &mut usize, c: char) -> bool {
-        if self.char_vec[*i] == c {
-            *i += 1;
-            true
-        } else {
-            false
-        }
-    }
-
-    fn parse_rank(&mut self, i: &mut usize, rank: &mut u8) -> bool {
-        *rank = char_to_rank(self.char_vec[*i]);
-        if *rank == u8::MAX {
-            return false;
-        }
-        *i += 1;
-        true
-    }
-
-    fn parse_suit(&mut self, i: &mut usize, suit: &mut u8) -> bool {
-        *suit = char_to_suit(self.char_vec[*i]);
-        if *suit == u8::MAX {
-            return false;
-        }
-        *i += 1;
-        true
+        let mut parser = RangeParser::new(&text);
+        let mut i: usize = 0;
+        while parser.parse_hand(&mut i) && parser.parse_char(&mut i, ',') {}
+        parser.range.remove_duplicates();
+        parser.range
     }
 
     /**
      * adds a single combo
      */
-    fn add_combo(&mut self, c1: u8, c2: u8, weight: u8) {
+    fn add_combo(&mut self, c1: u8, c2: u8, weight: f32) {
         // error: if out of bounds
         if c1 > 51 || c2 > 51 {
             return;
@@ -280,7 +315,14 @@ impl HandRange {
     /**
      * add combos rank1, rank2 -> 12
      */
-    fn add_combos_plus(&mut self, rank1: u8, rank2: u8, suited: bool, offsuited: bool, weight: u8) {
+    fn add_combos_plus(
+        &mut self,
+        rank1: u8,
+        rank2: u8,
+        suited: bool,
+        offsuited: bool,
+        weight: f32,
+    ) {
         if rank1 == rank2 {
             // add paired hands 22->AA
             for r in rank1..13 {
@@ -297,7 +339,7 @@ impl HandRange {
     /**
      * add suited and/or offsuit combos
      */
-    fn add_combos(&mut self, rank1: u8, rank2: u8, suited: bool, offsuited: bool, weight: u8) {
+    fn add_combos(&mut self, rank1: u8, rank2: u8, suited: bool, offsuited: bool, weight: f32) {
         if suited && rank1 != rank2 {
             // add suited combos
             for suit in 0..4 {
@@ -323,7 +365,7 @@ impl HandRange {
     fn add_all(&mut self) {
         for c1 in 0..CARD_COUNT {
             for c2 in 0..c1 {
-                self.add_combo(c1, c2, 100);
+                self.add_combo(c1, c2, 100.0);
             }
         }
     }
@@ -339,6 +381,198 @@ impl HandRange {
     }
 }
 
+impl FromStr for HandRange {
+    type Err = HandRangeError;
+
+    /// Parse an equilab-like range string, failing on any invalid hand
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::HandRange;
+    /// let range: HandRange = "JJ+, AKs@50".parse().unwrap();
+    /// assert_eq!(range.hands.len(), 28);
+    /// assert!("AKx,QQ".parse::<HandRange>().is_err());
+    /// ```
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("random") {
+            return Ok(HandRange::from_string("random".to_string()));
+        }
+        let mut parser = RangeParser::new(text);
+        let mut i: usize = 0;
+        while !parser.at_end(i) {
+            parser.skip_whitespace(&mut i);
+            let item_start = i;
+            let ok = parser.parse_hand(&mut i) && {
+                parser.skip_whitespace(&mut i);
+                parser.at_end(i) || parser.parse_char(&mut i, ',')
+            };
+            if !ok {
+                // report the comma separated item from the original text
+                let item = parser.chars[..item_start]
+                    .iter()
+                    .filter(|&&c| c == ',')
+                    .count();
+                let hand = text.split(',').nth(item).unwrap_or(text).trim();
+                return Err(HandRangeError::InvalidHand(hand.to_string()));
+            }
+        }
+        parser.range.remove_duplicates();
+        Ok(parser.range)
+    }
+}
+
+/// Reads the hands of an equilab-like range string into a range
+struct RangeParser {
+    /// lowercase characters of the range string followed by a space
+    chars: Vec<char>,
+    range: HandRange,
+}
+
+impl RangeParser {
+    fn new(text: &str) -> Self {
+        let mut chars: Vec<char> = text.to_lowercase().chars().collect();
+        chars.push(' ');
+        RangeParser {
+            chars,
+            range: HandRange::new(),
+        }
+    }
+
+    /// Whether only the trailing space is left
+    fn at_end(&self, i: usize) -> bool {
+        i + 1 >= self.chars.len()
+    }
+
+    fn skip_whitespace(&self, i: &mut usize) {
+        while !self.at_end(*i) && self.chars[*i].is_whitespace() {
+            *i += 1;
+        }
+    }
+
+    fn parse_hand(&mut self, i: &mut usize) -> bool {
+        let backtrack = *i;
+
+        let explicit_suits: bool;
+        let mut weight: f32 = 100.0;
+        let mut r1: u8 = u8::MAX;
+        let mut r2: u8 = u8::MAX;
+        let mut s1: u8 = u8::MAX;
+        let mut s2: u8 = u8::MAX;
+
+        if !self.parse_rank(i, &mut r1) {
+            return false;
+        }
+        explicit_suits = self.parse_suit(i, &mut s1);
+        if !self.parse_rank(i, &mut r2) {
+            *i = backtrack;
+            return false;
+        }
+        if explicit_suits && !self.parse_suit(i, &mut s2) {
+            *i = backtrack;
+            return false;
+        }
+        if explicit_suits {
+            let c1 = 4 * r1 + s1;
+            let c2 = 4 * r2 + s2;
+            if c1 == c2 {
+                *i = backtrack;
+                return false;
+            }
+            if !self.parse_optional_weight(i, &mut weight) {
+                *i = backtrack;
+                return false;
+            }
+            self.range.add_combo(c1, c2, weight);
+        } else {
+            let mut suited = true;
+            let mut offsuited = true;
+            if self.parse_char(i, 'o') {
+                suited = false;
+            } else if self.parse_char(i, 's') {
+                offsuited = false;
+            }
+            if self.parse_char(i, '+') {
+                if !self.parse_optional_weight(i, &mut weight) {
+                    *i = backtrack;
+                    return false;
+                }
+                self.range
+                    .add_combos_plus(r1, r2, suited, offsuited, weight);
+            } else {
+                if !self.parse_optional_weight(i, &mut weight) {
+                    *i = backtrack;
+                    return false;
+                }
+                self.range.add_combos(r1, r2, suited, offsuited, weight);
+            }
+        }
+
+        true
+    }
+
+    /// Parse "@weight" if present, failing if the weight is invalid
+    fn parse_optional_weight(&mut self, i: &mut usize, weight: &mut f32) -> bool {
+        !self.parse_char(i, '@') || self.parse_weight(i, weight)
+    }
+
+    fn parse_weight(&self, i: &mut usize, weight: &mut f32) -> bool {
+        let backtrack = *i;
+        let mut number = String::new();
+        while self.chars[*i].is_ascii_digit() {
+            number.push(self.chars[*i]);
+            *i += 1;
+        }
+        // optional fractional part
+        if !number.is_empty() && self.chars[*i] == '.' {
+            number.push('.');
+            *i += 1;
+            while self.chars[*i].is_ascii_digit() {
+                number.push(self.chars[*i]);
+                *i += 1;
+            }
+        }
+        match number.parse::<f32>() {
+            Ok(w) if w <= 100.0 => {
+                *weight = w;
+                true
+            }
+            _ => {
+                *i = backtrack;
+                false
+            }
+        }
+    }
+
+    fn parse_char(&mut self, i: &mut usize, c: char) -> bool {
+        if self.chars[*i] == c {
+            *i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_rank(&mut self, i: &mut usize, rank: &mut u8) -> bool {
+        *rank = char_to_rank(self.chars[*i]);
+        if *rank == u8::MAX {
+            return false;
+        }
+        *i += 1;
+        true
+    }
+
+    fn parse_suit(&mut self, i: &mut usize, suit: &mut u8) -> bool {
+        *suit = char_to_suit(self.chars[*i]);
+        if *suit == u8::MAX {
+            return false;
+        }
+        *i += 1;
+        true
+    }
+}
+
 /// Convert lowercase rank char to u8
 ///
 /// # Example
@@ -431,6 +665,40 @@ pub fn mask_to_string(card_mask: u64) -> String {
     card_str
 }
 
+/// Serialize 64 bit card masks such as boards as card strings
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// #[derive(Serialize, Deserialize)]
+/// struct Spot {
+///     #[serde(with = "rust_poker::hand_range::card_mask_serde")]
+///     board: u64,
+/// }
+/// let spot: Spot = serde_json::from_str(r#"{"board": "As7d2c"}"#).unwrap();
+/// assert_eq!(serde_json::to_string(&spot).unwrap(), r#"{"board":"2c7dAs"}"#);
+/// ```
+pub mod card_mask_serde {
+    use super::{get_card_mask, mask_to_string};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(card_mask: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&mask_to_string(*card_mask))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let card_mask = get_card_mask(&text);
+        // get_card_mask returns 0 for invalid strings and ignores duplicates
+        if card_mask.count_ones() as usize * 2 != text.len() {
+            return Err(D::Error::custom(format!("invalid cards: {}", text)));
+        }
+        Ok(card_mask)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -465,15 +733,15 @@ mod tests {
     fn test_hand_range_remove_duplicates() {
         // add same range twice and remove
         let mut c = HandRange::new();
-        c.add_combos(1, 1, true, true, 100);
-        c.add_combos(1, 1, true, true, 100);
+        c.add_combos(1, 1, true, true, 100.0);
+        c.add_combos(1, 1, true, true, 100.0);
         assert_eq!(c.hands.len(), 12);
         c.remove_duplicates();
         assert_eq!(c.hands.len(), 6);
         // two different ranges, no change
         c = HandRange::new();
-        c.add_combos(1, 0, true, false, 100);
-        c.add_combos(1, 0, false, true, 100);
+        c.add_combos(1, 0, true, false, 100.0);
+        c.add_combos(1, 0, false, true, 100.0);
         assert_eq!(c.hands.len(), 16);
         c.remove_duplicates();
         assert_eq!(c.hands.len(), 16);
@@ -483,11 +751,11 @@ mod tests {
     fn test_hand_range_add_combo() {
         // invalid: card index out of bounds
         let mut c = HandRange::new();
-        c.add_combo(52, 0, 100);
+        c.add_combo(52, 0, 100.0);
         assert_eq!(c.hands.len(), 0);
         // invalid: same card
         c = HandRange::new();
-        c.add_combo(0, 0, 100);
+        c.add_combo(0, 0, 100.0);
         assert_eq!(c.hands.len(), 0);
     }
 
@@ -495,19 +763,19 @@ mod tests {
     fn test_hand_range_add_combos() {
         // valid test add paired hand
         let mut c = HandRange::new();
-        c.add_combos(1, 1, true, true, 100);
+        c.add_combos(1, 1, true, true, 100.0);
         assert_eq!(c.hands.len(), 6);
         // valid: test add suited hand
         c = HandRange::new();
-        c.add_combos(1, 0, true, false, 100);
+        c.add_combos(1, 0, true, false, 100.0);
         assert_eq!(c.hands.len(), 4);
         // valid: test add offsuite hand
         c = HandRange::new();
-        c.add_combos(1, 0, false, true, 100);
+        c.add_combos(1, 0, false, true, 100.0);
         assert_eq!(c.hands.len(), 12);
         // valid: test add both
         c = HandRange::new();
-        c.add_combos(1, 0, true, true, 100);
+        c.add_combos(1, 0, true, true, 100.0);
         assert_eq!(c.hands.len(), 16);
     }
 
@@ -547,4 +815,79 @@ mod tests {
         c = HandRange::from_string("as2h@50,AA@25,KK@100".to_string());
         assert_eq!(c.hands.len(), 13);
     }
+
+    #[test]
+    fn test_hand_range_sample() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+        let mut rng = SmallRng::seed_from_u64(1);
+        let c = HandRange::from_string("AA@25,KK@75,QQ@0".to_string());
+        let dead_mask = get_card_mask("Ks");
+        let mut aces = 0;
+        for _ in 0..10000 {
+            let combo = c.sample(&mut rng, dead_mask).unwrap();
+            assert_eq!(combo.mask() & dead_mask, 0);
+            assert_ne!(combo.0 >> 2, 10);
+            if combo.0 >> 2 == 12 {
+                aces += 1;
+            }
+        }
+        // 6 AA combos at 25 vs 3 KK combos at 75
+        assert!(aces > 3800 && aces < 4200);
+        assert!(c.sample(&mut rng, get_card_mask("AsAhKsKh")).is_some());
+        assert!(c.sample(&mut rng, get_card_mask("AsAhAdKsKhKd")).is_none());
+    }
+
+    #[test]
+    fn test_combo_serde() {
+        let combo = Combo(49, 46, 37.25);
+        let json = serde_json::to_string(&combo).unwrap();
+        assert_eq!(json, r#"{"hand":"AhKd","weight":37.25}"#);
+        let c: Combo = serde_json::from_str(r#"{"hand":"KdAh","weight":37.25}"#).unwrap();
+        assert_eq!((c.0, c.1, c.2), (49, 46, 37.25));
+        let c: Combo = serde_json::from_str(r#"{"hand":"2s2h"}"#).unwrap();
+        assert_eq!((c.0, c.1, c.2), (1, 0, 100.0));
+        assert!(serde_json::from_str::<Combo>(r#"{"hand":"AhAh"}"#).is_err());
+        assert!(serde_json::from_str::<Combo>(r#"{"hand":"AhKd","weight":101}"#).is_err());
+    }
+
+    #[test]
+    fn test_hand_range_parse_strict() {
+        let range: HandRange = "as2h@50, AA@25,KK".parse().unwrap();
+        assert_eq!(range.hands.len(), 13);
+        assert_eq!(" random ".parse::<HandRange>().unwrap().hands.len(), 1326);
+        assert!("".parse::<HandRange>().unwrap().hands.is_empty());
+        for text in &["AKx,QQ", "garbage", "QQ,AK@101", "AA@,KK", "AsAs", "AA KK"] {
+            assert!(text.parse::<HandRange>().is_err(), "{}", text);
+        }
+        match "QQ,AKx@50,KK".parse::<HandRange>() {
+            Err(HandRangeError::InvalidHand(hand)) => assert_eq!(hand, "AKx@50"),
+            _ => panic!("expected an invalid hand"),
+        }
+    }
+
+    #[test]
+    fn test_card_mask_serde() {
+        #[derive(Serialize, Deserialize)]
+        struct Board(#[serde(with = "card_mask_serde")] u64);
+        let board: Board = serde_json::from_str(r#""Ah7d2c""#).unwrap();
+        assert_eq!(board.0, get_card_mask("Ah7d2c"));
+        assert!(serde_json::from_str::<Board>(r#""Ah7x""#).is_err());
+        assert!(serde_json::from_str::<Board>(r#""AhAh""#).is_err());
+        let board: Board = serde_json::from_str("\"\"").unwrap();
+        assert_eq!(board.0, 0);
+    }
+
+    #[test]
+    fn test_hand_range_fractional_weight() {
+        let c = HandRange::from_string("AKs@37.25,QQ@0.5,JJ".to_string());
+        assert_eq!(c.hands.len(), 16);
+        for combo in &c.hands {
+            match combo.0 >> 2 {
+                12 => assert_eq!(combo.2, 37.25),
+                10 => assert_eq!(combo.2, 0.5),
+                _ => assert_eq!(combo.2, 100.0),
+            }
+        }
+    }
 }
@@ -7,6 +7,7 @@
 ///  - monte carlo range vs. range equity calculations
 ///  - full enumeration for exact equities
 ///  - fast hand evaluation
+///  - omaha range vs. range equity calculations
 ///
 /// ## Equity Calculator
 ///
@@ -45,9 +46,14 @@ pub use hand_indexer::{HandIndex, HandIndexer};
 
 pub use read_write;
 
+pub mod card_abstraction;
+pub mod combo_counter;
 pub mod constants;
 pub mod hand_evaluator;
 pub mod hand_range;
+pub mod omaha;
 pub mod range_filter;
+pub mod range_format;
+pub mod range_library;
 
 pub mod equity_calculator;
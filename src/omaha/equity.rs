@@ -0,0 +1,705 @@
+use crossbeam::atomic::AtomicCell;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::SmallRng;
+use rand::{thread_rng, Rng, SeedableRng};
+
+use super::evaluator::{board_triples, evaluate_subsets, hole_pairs, MAX_HOLE_PAIRS};
+use super::{OmahaError, OmahaRange};
+use crate::constants::CARD_COUNT;
+use crate::equity_calculator::{
+    batch_seed, BoardSampling, EquityOptions, EquityResult, PendingBatches, SimulatorError,
+    StopReason, StoppingRules, MONTE_CARLO_BATCH_SIZE,
+};
+use crate::hand_evaluator::Hand;
+
+/// Max number of players in an omaha equity calculation
+pub const MAX_OMAHA_PLAYERS: usize = 10;
+const MIN_PLAYERS: usize = 2;
+const BOARD_CARDS: usize = 5;
+/// Attempts at dealing non conflicting hands before giving up
+const MAX_DEAL_ATTEMPTS: usize = 1000;
+/// Number of matchups each thread takes at a time in exact calculations
+const ENUM_BATCH_SIZE: u64 = 64;
+
+/// Calculates exact omaha range vs range equities
+///
+/// Every matchup is enumerated against every runout, so this is only
+/// practical for small ranges or later streets.
+/// If `time_limit` runs out the result only covers the matchups enumerated so far
+/// and is not exact
+///
+/// # Arguments
+///
+/// * `ranges` Array of omaha ranges with the same hand size
+/// * `options` Board, dead cards and thread count
+///
+/// # Example
+/// ```
+/// use rust_poker::omaha::{omaha_exact_equity, OmahaRange};
+/// use rust_poker::equity_calculator::EquityOptions;
+/// use rust_poker::hand_range::get_card_mask;
+/// let ranges = [
+///     OmahaRange::parse("AsAhKsKh", 4).unwrap(),
+///     OmahaRange::parse("JcTc9d8d", 4).unwrap(),
+/// ];
+/// let options = EquityOptions {
+///     board_mask: get_card_mask("Qc7d2h"),
+///     ..EquityOptions::default()
+/// };
+/// let result = omaha_exact_equity(&ranges, &options).unwrap();
+/// assert!(result.exact);
+/// // the overpairs are ahead of the wrap draw
+/// assert!(result.equities[0] > 0.7 && result.equities[0] < 0.8);
+/// ```
+pub fn omaha_exact_equity(
+    ranges: &[OmahaRange],
+    options: &EquityOptions,
+) -> Result<EquityResult, OmahaError> {
+    let sim = OmahaSimulator::prepare(ranges, options)?;
+    sim.matchup_count()?;
+    crossbeam::scope(|scope| {
+        for _ in 0..options.n_threads {
+            scope.spawn(|_| sim.enumerate_all());
+        }
+    })
+    .unwrap();
+    Ok(sim.get_result(true))
+}
+
+/// Runs a monte carlo simulation to calculate omaha range vs range equity
+///
+/// Stops on the same targets and budgets as `approx_equity_with_options`,
+/// runs with the same seed give the same results for any thread count
+///
+/// # Arguments
+///
+/// * `ranges` Array of omaha ranges with the same hand size
+/// * `options` Board, dead cards, thread count and stopping rules
+///
+/// # Example
+/// ```
+/// use rust_poker::omaha::{omaha_approx_equity, OmahaRange};
+/// use rust_poker::equity_calculator::EquityOptions;
+/// let ranges = [
+///     OmahaRange::parse("AA**$ds", 4).unwrap(),
+///     OmahaRange::parse("random", 4).unwrap(),
+/// ];
+/// let options = EquityOptions {
+///     stdev_target: 0.005,
+///     ..EquityOptions::default()
+/// };
+/// let result = omaha_approx_equity(&ranges, &options).unwrap();
+/// assert!(result.equities[0] > 0.6);
+/// ```
+pub fn omaha_approx_equity(
+    ranges: &[OmahaRange],
+    options: &EquityOptions,
+) -> Result<EquityResult, OmahaError> {
+    let sim = OmahaSimulator::prepare(ranges, options)?;
+    crossbeam::scope(|scope| {
+        for _ in 0..options.n_threads {
+            scope.spawn(|_| sim.sim_monte_carlo());
+        }
+    })
+    .unwrap();
+    Ok(sim.get_result(false))
+}
+
+/// hand with its two card subsets ready for evaluation
+#[derive(Debug, Clone, Copy)]
+struct PreparedHand {
+    mask: u64,
+    weight: f64,
+    pairs: [Hand; MAX_HOLE_PAIRS],
+    n_pairs: usize,
+}
+
+/// results shared between threads
+#[derive(Debug)]
+struct OmahaResults {
+    /// weighted wins keyed by the mask of winning players
+    wins_by_mask: Vec<f64>,
+    eval_count: u64,
+    /// sum of each player's batch equity
+    batch_sum: Vec<f64>,
+    /// sum of each player's squared batch equity
+    batch_sum2: Vec<f64>,
+    batch_count: f64,
+    /// standard error of each player's equity
+    stdev: Vec<f64>,
+    /// batches waiting for earlier batches to be merged
+    pending: PendingBatches<Vec<f64>>,
+}
+
+/// omaha equity calculator main structure
+#[derive(Debug)]
+struct OmahaSimulator {
+    ranges: Vec<Vec<PreparedHand>>,
+    /// fixed board cards
+    board: Vec<u8>,
+    /// fixed board and dead cards as 64bit mask
+    used_mask: u64,
+    n_players: usize,
+    options: EquityOptions,
+    stopping_rules: StoppingRules,
+    seed: u64,
+    results: Mutex<OmahaResults>,
+    /// matchup position for exact calculations
+    enum_pos: Mutex<u64>,
+    /// index of the next monte carlo batch to run
+    next_batch: AtomicCell<u64>,
+    stopped: AtomicCell<bool>,
+    stop_reason: AtomicCell<Option<StopReason>>,
+    start_time: Instant,
+}
+
+impl OmahaSimulator {
+    fn prepare(ranges: &[OmahaRange], options: &EquityOptions) -> Result<Self, OmahaError> {
+        if options.short_deck {
+            return Err(OmahaError::UnsupportedOption("short_deck"));
+        }
+        if options.combo_equity {
+            return Err(OmahaError::UnsupportedOption("combo_equity"));
+        }
+        if options.stratify_combos {
+            return Err(OmahaError::UnsupportedOption("stratify_combos"));
+        }
+        if options.board_sampling != BoardSampling::Random {
+            return Err(OmahaError::UnsupportedOption("board_sampling"));
+        }
+        if ranges.len() < MIN_PLAYERS {
+            return Err(SimulatorError::TooFewPlayers.into());
+        }
+        if ranges.len() > MAX_OMAHA_PLAYERS {
+            return Err(SimulatorError::TooManyPlayers.into());
+        }
+        let hand_size = ranges[0].hand_size();
+        if ranges.iter().any(|r| r.hand_size() != hand_size) {
+            return Err(OmahaError::MixedHandSizes);
+        }
+        let board_count = options.board_mask.count_ones() as usize;
+        if board_count > BOARD_CARDS {
+            return Err(SimulatorError::TooManyBoardCards.into());
+        }
+        let used_mask = options.board_mask | options.dead_mask;
+        let cards_needed =
+            used_mask.count_ones() as usize + BOARD_CARDS - board_count + hand_size * ranges.len();
+        if cards_needed > usize::from(CARD_COUNT) {
+            return Err(SimulatorError::TooManyDeadCards.into());
+        }
+
+        let mut prepared = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let hands: Vec<PreparedHand> = range
+                .hands()
+                .iter()
+                .filter(|h| (h.mask & used_mask) == 0)
+                .map(|h| {
+                    let (pairs, n_pairs) = hole_pairs(&h.cards());
+                    PreparedHand {
+                        mask: h.mask,
+                        weight: f64::from(h.weight),
+                        pairs,
+                        n_pairs,
+                    }
+                })
+                .collect();
+            if hands.is_empty() {
+                return Err(SimulatorError::ConflictingRanges.into());
+            }
+            prepared.push(hands);
+        }
+
+        let n_players = ranges.len();
+        Ok(OmahaSimulator {
+            ranges: prepared,
+            board: (0..CARD_COUNT)
+                .filter(|c| (options.board_mask >> c) & 1 != 0)
+                .collect(),
+            used_mask,
+            n_players,
+            options: options.clone(),
+            stopping_rules: StoppingRules::new(options),
+            seed: options.seed.unwrap_or_else(|| thread_rng().gen()),
+            results: Mutex::new(OmahaResults {
+                wins_by_mask: vec![0f64; 1 << n_players],
+                eval_count: 0,
+                batch_sum: vec![0f64; n_players],
+                batch_sum2: vec![0f64; n_players],
+                batch_count: 0.0,
+                stdev: vec![0f64; n_players],
+                pending: PendingBatches::default(),
+            }),
+            enum_pos: Mutex::new(0),
+            next_batch: AtomicCell::new(0),
+            stopped: AtomicCell::new(false),
+            stop_reason: AtomicCell::new(None),
+            start_time: Instant::now(),
+        })
+    }
+
+    /// Number of matchups to enumerate in exact calculations
+    fn matchup_count(&self) -> Result<u64, OmahaError> {
+        self.ranges
+            .iter()
+            .try_fold(1u64, |count, r| count.checked_mul(r.len() as u64))
+            .ok_or_else(|| SimulatorError::TooManyCombos.into())
+    }
+
+    /// Stop all threads, only the first reason given is kept
+    fn stop(&self, reason: StopReason) {
+        let _ = self.stop_reason.compare_exchange(None, Some(reason));
+        self.stopped.store(true);
+    }
+
+    /// Stop all threads if the time limit ran out
+    fn check_time_limit(&self) -> bool {
+        let out_of_time = self
+            .options
+            .time_limit
+            .is_some_and(|limit| self.start_time.elapsed() >= limit);
+        if out_of_time {
+            self.stop(StopReason::TimeLimit);
+        }
+        out_of_time
+    }
+
+    fn get_result(&self, exact: bool) -> EquityResult {
+        let results = self.results.lock().unwrap();
+        let total = results.wins_by_mask.iter().sum::<f64>().max(1e-300);
+        let mut equities = vec![0f64; self.n_players];
+        let mut wins = vec![0f64; self.n_players];
+        let mut ties = vec![0f64; self.n_players];
+        let mut wins_by_mask = BTreeMap::new();
+        for (mask, w) in results.wins_by_mask.iter().enumerate() {
+            if *w == 0.0 {
+                continue;
+            }
+            let w = w / total;
+            let winner_count = mask.count_ones();
+            wins_by_mask.insert(mask as u32, w);
+            for i in 0..self.n_players {
+                if (mask & (1 << i)) == 0 {
+                    continue;
+                }
+                equities[i] += w / f64::from(winner_count);
+                if winner_count == 1 {
+                    wins[i] += w;
+                } else {
+                    ties[i] += w;
+                }
+            }
+        }
+        EquityResult {
+            equities,
+            wins,
+            ties,
+            wins_by_mask,
+            eval_count: results.eval_count,
+            exact: exact && self.stop_reason.load().is_none(),
+            stop_reason: self.stop_reason.load().unwrap_or(StopReason::Completed),
+            stdev: results.stdev.clone(),
+            combo_equities: None,
+        }
+    }
+
+    /// Score each player on a complete board and return the winner mask
+    fn evaluate_players(&self, hands: &[&PreparedHand], board: &[u8]) -> usize {
+        let triples = board_triples(board);
+        let mut winner_mask = 0usize;
+        let mut best_score = 0u16;
+        for (i, hand) in hands.iter().enumerate() {
+            let score = evaluate_subsets(&hand.pairs[..hand.n_pairs], &triples);
+            if score > best_score {
+                best_score = score;
+                winner_mask = 1 << i;
+            } else if score == best_score {
+                winner_mask |= 1 << i;
+            }
+        }
+        winner_mask
+    }
+
+    fn reserve_batch(&self, total: u64) -> (u64, u64) {
+        let mut enum_pos = self.enum_pos.lock().unwrap();
+        if self.stopped.load() {
+            return (*enum_pos, *enum_pos);
+        }
+        let start = *enum_pos;
+        let end = std::cmp::min(total, start + ENUM_BATCH_SIZE);
+        *enum_pos = end;
+        (start, end)
+    }
+
+    fn enumerate_all(&self) {
+        let total = self.matchup_count().unwrap_or(0);
+        let mut wins_by_mask = vec![0f64; 1 << self.n_players];
+        let mut eval_count = 0u64;
+        let mut hands: Vec<&PreparedHand> = Vec::with_capacity(self.n_players);
+        let mut board = [0u8; BOARD_CARDS];
+        board[..self.board.len()].copy_from_slice(&self.board);
+        loop {
+            let (start, end) = self.reserve_batch(total);
+            if start >= end {
+                break;
+            }
+            for pos in start..end {
+                if self.stopped.load() || self.check_time_limit() {
+                    break;
+                }
+                // decode the matchup position into one hand per player
+                hands.clear();
+                let mut rest = pos;
+                let mut used_mask = self.used_mask;
+                for range in &self.ranges {
+                    let hand = &range[(rest % range.len() as u64) as usize];
+                    rest /= range.len() as u64;
+                    if (used_mask & hand.mask) != 0 {
+                        break;
+                    }
+                    used_mask |= hand.mask;
+                    hands.push(hand);
+                }
+                if hands.len() != self.n_players {
+                    continue;
+                }
+                let weight: f64 = hands.iter().map(|h| h.weight).product();
+                let deck: Vec<u8> = (0..CARD_COUNT)
+                    .filter(|c| (used_mask >> c) & 1 == 0)
+                    .collect();
+                self.enumerate_board(
+                    &hands,
+                    &mut board,
+                    self.board.len(),
+                    &deck,
+                    0,
+                    weight,
+                    &mut wins_by_mask,
+                    &mut eval_count,
+                );
+            }
+        }
+
+        let mut results = self.results.lock().unwrap();
+        for (total, w) in results.wins_by_mask.iter_mut().zip(wins_by_mask.iter()) {
+            *total += w;
+        }
+        results.eval_count += eval_count;
+    }
+
+    /// Deal every completion of the board from `deck[start..]`
+    #[allow(clippy::too_many_arguments)]
+    fn enumerate_board(
+        &self,
+        hands: &[&PreparedHand],
+        board: &mut [u8; BOARD_CARDS],
+        board_count: usize,
+        deck: &[u8],
+        start: usize,
+        weight: f64,
+        wins_by_mask: &mut [f64],
+        eval_count: &mut u64,
+    ) {
+        if board_count == BOARD_CARDS {
+            wins_by_mask[self.evaluate_players(hands, board)] += weight;
+            *eval_count += 1;
+            return;
+        }
+        for i in start..deck.len() {
+            board[board_count] = deck[i];
+            self.enumerate_board(
+                hands,
+                board,
+                board_count + 1,
+                deck,
+                i + 1,
+                weight,
+                wins_by_mask,
+                eval_count,
+            );
+        }
+    }
+
+    fn sim_monte_carlo(&self) {
+        let card_dist: Uniform<u8> = Uniform::from(0..CARD_COUNT);
+        let hand_dists: Vec<Uniform<usize>> = self
+            .ranges
+            .iter()
+            .map(|r| Uniform::from(0..r.len()))
+            .collect();
+        let mut hands: Vec<&PreparedHand> = Vec::with_capacity(self.n_players);
+        let mut board = [0u8; BOARD_CARDS];
+        board[..self.board.len()].copy_from_slice(&self.board);
+
+        while !self.stopped.load() {
+            if self.check_time_limit() {
+                break;
+            }
+            // every batch has its own random stream
+            let batch_idx = self.next_batch.fetch_add(1);
+            let mut rng = SmallRng::seed_from_u64(batch_seed(self.seed, batch_idx));
+            let mut wins_by_mask = vec![0f64; 1 << self.n_players];
+            for _ in 0..MONTE_CARLO_BATCH_SIZE {
+                let mut used_mask = match self.deal_hands(&mut rng, &hand_dists, &mut hands) {
+                    Some(mask) => mask,
+                    None => {
+                        self.stop(StopReason::NoValidDeal);
+                        return;
+                    }
+                };
+                for card in board.iter_mut().skip(self.board.len()) {
+                    let mut c = card_dist.sample(&mut rng);
+                    while (used_mask >> c) & 1 != 0 {
+                        c = card_dist.sample(&mut rng);
+                    }
+                    used_mask |= 1u64 << c;
+                    *card = c;
+                }
+                let weight: f64 = hands.iter().map(|h| h.weight).product();
+                wins_by_mask[self.evaluate_players(&hands, &board)] += weight;
+            }
+            self.submit_batch(batch_idx, wins_by_mask);
+        }
+    }
+
+    /// Deal one hand to each player by rejection sampling
+    ///
+    /// Returns the mask of used cards or `None` if no deal was found
+    fn deal_hands<'a, R: Rng>(
+        &'a self,
+        rng: &mut R,
+        hand_dists: &[Uniform<usize>],
+        hands: &mut Vec<&'a PreparedHand>,
+    ) -> Option<u64> {
+        for _ in 0..MAX_DEAL_ATTEMPTS {
+            hands.clear();
+            let mut used_mask = self.used_mask;
+            for (range, dist) in self.ranges.iter().zip(hand_dists.iter()) {
+                let hand = &range[dist.sample(rng)];
+                if (used_mask & hand.mask) != 0 {
+                    break;
+                }
+                used_mask |= hand.mask;
+                hands.push(hand);
+            }
+            if hands.len() == self.n_players {
+                return Some(used_mask);
+            }
+        }
+        None
+    }
+
+    /// Merge finished monte carlo batches in index order
+    ///
+    /// Batches after the one that meets a stopping rule are dropped,
+    /// so results only depend on the seed
+    fn submit_batch(&self, batch_idx: u64, batch_wins: Vec<f64>) {
+        let mut results = self.results.lock().unwrap();
+        results.pending.insert(batch_idx, batch_wins);
+        while !self.stopped.load() {
+            let batch_wins = match results.pending.pop_next() {
+                Some(b) => b,
+                None => break,
+            };
+            self.merge_batch(&mut results, &batch_wins);
+        }
+    }
+
+    /// Merge a monte carlo batch into the results and check the stopping rules
+    fn merge_batch(&self, results: &mut OmahaResults, batch_wins: &[f64]) {
+        let batch_total: f64 = batch_wins.iter().sum();
+        let mut batch_equity = vec![0f64; self.n_players];
+        for (mask, w) in batch_wins.iter().enumerate() {
+            results.wins_by_mask[mask] += w;
+            let winner_count = mask.count_ones();
+            for (i, e) in batch_equity.iter_mut().enumerate() {
+                if (mask & (1 << i)) != 0 {
+                    *e += w / f64::from(winner_count);
+                }
+            }
+        }
+        results.eval_count += MONTE_CARLO_BATCH_SIZE;
+        results.batch_count += 1.0;
+        for (i, e) in batch_equity.iter().enumerate() {
+            let equity = e / (batch_total + 1e-9);
+            results.batch_sum[i] += equity;
+            results.batch_sum2[i] += equity * equity;
+            results.stdev[i] = (1e-9 + results.batch_sum2[i]
+                - results.batch_sum[i] * results.batch_sum[i] / results.batch_count)
+                .sqrt()
+                / results.batch_count;
+        }
+
+        if let Some(reason) =
+            self.stopping_rules
+                .reached(results.batch_count, results.eval_count, &results.stdev)
+        {
+            self.stop(reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_exact_river() {
+        let ranges = [
+            OmahaRange::parse("AsAh2c3c", 4).unwrap(),
+            OmahaRange::parse("KsKh4c5c", 4).unwrap(),
+        ];
+        let options = EquityOptions {
+            board_mask: get_card_mask("Kd7d8s9hTc"),
+            ..EquityOptions::default()
+        };
+        let result = omaha_exact_equity(&ranges, &options).unwrap();
+        assert_eq!(result.eval_count, 1);
+        assert_eq!(result.equities, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_exact_turn_outs() {
+        let ranges = [
+            OmahaRange::parse("AsAhKdQd", 4).unwrap(),
+            OmahaRange::parse("9s9h8c7c", 4).unwrap(),
+        ];
+        let options = EquityOptions {
+            board_mask: get_card_mask("9d5c2sJh"),
+            ..EquityOptions::default()
+        };
+        let result = omaha_exact_equity(&ranges, &options).unwrap();
+        assert_eq!(result.eval_count, 40);
+        // a ten makes the straight for the aces and an ace makes a bigger set
+        let outs = 4.0 + 2.0;
+        assert!((result.equities[0] - outs / 40.0).abs() < 1e-9);
+        assert_eq!(result.stop_reason, StopReason::Completed);
+    }
+
+    #[test]
+    fn test_approx_matches_exact() {
+        let ranges = [
+            OmahaRange::parse("AA**$ds", 4).unwrap(),
+            OmahaRange::parse("KsKdQsQd", 4).unwrap(),
+        ];
+        let options = EquityOptions {
+            board_mask: get_card_mask("Ah9c4d3s"),
+            stdev_target: 0.002,
+            seed: Some(3),
+            ..EquityOptions::default()
+        };
+        let approx = omaha_approx_equity(&ranges, &options).unwrap();
+        assert_eq!(approx.stop_reason, StopReason::StdevTarget);
+        let exact = omaha_exact_equity(&ranges, &options).unwrap();
+        assert!((approx.equities[0] - exact.equities[0]).abs() < 0.01);
+        assert!((exact.equities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_five_card_multiway() {
+        let ranges = [
+            OmahaRange::parse("AA***", 5).unwrap(),
+            OmahaRange::parse("random", 5).unwrap(),
+            OmahaRange::parse("random", 5).unwrap(),
+        ];
+        let options = EquityOptions {
+            max_batches: Some(20),
+            stdev_target: 0.0,
+            ..EquityOptions::default()
+        };
+        let result = omaha_approx_equity(&ranges, &options).unwrap();
+        assert_eq!(result.stop_reason, StopReason::MaxBatches);
+        assert!(result.equities[0] > result.equities[1]);
+        assert!((result.equities[1] - result.equities[2]).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_seeded_runs_match() {
+        let ranges = [
+            OmahaRange::parse("AA**", 4).unwrap(),
+            OmahaRange::parse("random", 4).unwrap(),
+        ];
+        let run = |n_threads| {
+            let options = EquityOptions {
+                n_threads,
+                seed: Some(11),
+                stdev_target: 0.004,
+                ..EquityOptions::default()
+            };
+            omaha_approx_equity(&ranges, &options).unwrap()
+        };
+        let single = run(1);
+        let multi = run(4);
+        assert_eq!(single.equities, multi.equities);
+        assert_eq!(single.eval_count, multi.eval_count);
+    }
+
+    #[test]
+    fn test_exact_time_limit() {
+        let ranges = [
+            OmahaRange::parse("random", 4).unwrap(),
+            OmahaRange::parse("random", 4).unwrap(),
+        ];
+        let options = EquityOptions {
+            time_limit: Some(std::time::Duration::from_millis(50)),
+            ..EquityOptions::default()
+        };
+        let start = Instant::now();
+        let result = omaha_exact_equity(&ranges, &options).unwrap();
+        assert!(start.elapsed().as_secs() < 10);
+        assert_eq!(result.stop_reason, StopReason::TimeLimit);
+        assert!(!result.exact);
+    }
+
+    #[test]
+    fn test_unsupported_options() {
+        let ranges = [
+            OmahaRange::parse("AAKK", 4).unwrap(),
+            OmahaRange::parse("random", 4).unwrap(),
+        ];
+        let short_deck = EquityOptions {
+            short_deck: true,
+            ..EquityOptions::default()
+        };
+        assert!(matches!(
+            omaha_approx_equity(&ranges, &short_deck),
+            Err(OmahaError::UnsupportedOption("short_deck"))
+        ));
+        let combo_equity = EquityOptions {
+            combo_equity: true,
+            ..EquityOptions::default()
+        };
+        assert!(matches!(
+            omaha_exact_equity(&ranges, &combo_equity),
+            Err(OmahaError::UnsupportedOption("combo_equity"))
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let four = OmahaRange::parse("AAKK", 4).unwrap();
+        let five = OmahaRange::parse("AAKKQ", 5).unwrap();
+        let options = EquityOptions::default();
+        assert!(matches!(
+            omaha_approx_equity(&[four.clone(), five], &options),
+            Err(OmahaError::MixedHandSizes)
+        ));
+        assert!(matches!(
+            omaha_approx_equity(std::slice::from_ref(&four), &options),
+            Err(OmahaError::Simulator(SimulatorError::TooFewPlayers))
+        ));
+        let options = EquityOptions {
+            board_mask: get_card_mask("AsAhAdAc2c"),
+            ..EquityOptions::default()
+        };
+        assert!(matches!(
+            omaha_exact_equity(&[four.clone(), four], &options),
+            Err(OmahaError::Simulator(SimulatorError::ConflictingRanges))
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::hand_evaluator::{evaluate, Hand, CARDS};
+
+/// Max number of two card subsets of 5 hole cards
+pub(super) const MAX_HOLE_PAIRS: usize = 10;
+/// Number of three card subsets of a 5 card board
+pub(super) const BOARD_TRIPLES: usize = 10;
+
+/// Every two card subset of the hole cards
+pub(super) fn hole_pairs(hole_cards: &[u8]) -> ([Hand; MAX_HOLE_PAIRS], usize) {
+    let mut pairs = [Hand::default(); MAX_HOLE_PAIRS];
+    let mut n_pairs = 0;
+    for i in 0..hole_cards.len() {
+        for j in i + 1..hole_cards.len() {
+            pairs[n_pairs] = Hand::from_hole_cards(hole_cards[i], hole_cards[j]);
+            n_pairs += 1;
+        }
+    }
+    (pairs, n_pairs)
+}
+
+/// Every three card subset of a complete board
+///
+/// Each subset includes the suit counter offset needed for evaluation
+pub(super) fn board_triples(board: &[u8]) -> [Hand; BOARD_TRIPLES] {
+    let mut triples = [Hand::default(); BOARD_TRIPLES];
+    let mut n = 0;
+    for i in 0..5 {
+        for j in i + 1..5 {
+            for k in j + 1..5 {
+                triples[n] = Hand::default()
+                    + CARDS[usize::from(board[i])]
+                    + CARDS[usize::from(board[j])]
+                    + CARDS[usize::from(board[k])];
+                n += 1;
+            }
+        }
+    }
+    triples
+}
+
+/// Best score of any two hole cards with any three board cards
+#[inline]
+pub(super) fn evaluate_subsets(pairs: &[Hand], triples: &[Hand; BOARD_TRIPLES]) -> u16 {
+    let mut best = 0;
+    for pair in pairs {
+        for triple in triples {
+            best = best.max(evaluate(&(*triple + *pair)));
+        }
+    }
+    best
+}
+
+/// Evaluates an omaha hand and returns score
+///
+/// The hand is made of exactly two of the hole cards and three of the board cards
+///
+/// # Arguments
+///
+/// * `hole_cards` 4 or 5 hole cards, indexed 4 * rank + suit
+/// * `board` 5 board cards
+///
+/// # Example
+/// ```
+/// use rust_poker::omaha::evaluate_omaha;
+/// use rust_poker::hand_evaluator::{evaluate, Hand};
+/// use rust_poker::hand_range::get_card_mask;
+/// // AsKsQsJs2h on the board and the 2s in hand is no flush in omaha
+/// let board = [48, 44, 40, 36, 1];
+/// let hole_cards = [0, 5, 9, 13];
+/// let flush = evaluate(&Hand::from_bit_mask(get_card_mask("AsKsQsJs2s")));
+/// assert!(evaluate_omaha(&hole_cards, &board) < flush);
+/// ```
+pub fn evaluate_omaha(hole_cards: &[u8], board: &[u8]) -> u16 {
+    let (pairs, n_pairs) = hole_pairs(hole_cards);
+    evaluate_subsets(&pairs[..n_pairs], &board_triples(board))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    fn cards(text: &str) -> Vec<u8> {
+        let mask = get_card_mask(text);
+        (0..52u8).filter(|c| (mask >> c) & 1 != 0).collect()
+    }
+
+    fn holdem_score(text: &str) -> u16 {
+        evaluate(&Hand::from_bit_mask(get_card_mask(text)))
+    }
+
+    #[test]
+    fn test_uses_two_hole_cards() {
+        let board = cards("AhKhQh2c3d");
+        // one heart in hand is no flush
+        assert_eq!(
+            evaluate_omaha(&cards("Jh9s8s7c"), &board),
+            holdem_score("AhKhQhJh9s")
+        );
+        // two hearts make the flush
+        assert_eq!(
+            evaluate_omaha(&cards("Jh9h8s7c"), &board),
+            holdem_score("AhKhQhJh9h")
+        );
+        // quads on board play as trips at best
+        let board = cards("AsAhAdAc2c");
+        assert_eq!(
+            evaluate_omaha(&cards("KsKh3d4d"), &board),
+            holdem_score("AsAhAdKsKh")
+        );
+    }
+
+    #[test]
+    fn test_five_card_hands() {
+        let board = cards("Ts9s2d3c4h");
+        assert_eq!(
+            evaluate_omaha(&cards("KsQsJd5h6h"), &board),
+            holdem_score("5h6h2d3c4h")
+        );
+    }
+}
@@ -0,0 +1,30 @@
+/*
+ * Range vs range equity for 4 card (PLO4) and 5 card (PLO5) omaha,
+ * where every hand is made of exactly two hole cards and three board cards
+ */
+
+mod equity;
+mod evaluator;
+mod range;
+
+use thiserror::Error;
+
+use crate::equity_calculator::SimulatorError;
+
+pub use equity::{omaha_approx_equity, omaha_exact_equity, MAX_OMAHA_PLAYERS};
+pub use evaluator::evaluate_omaha;
+pub use range::{OmahaHand, OmahaRange};
+
+#[derive(Debug, Error)]
+pub enum OmahaError {
+    #[error("invalid omaha range: {0}")]
+    InvalidRange(String),
+    #[error("omaha hands must have 4 or 5 cards, not {0}")]
+    InvalidHandSize(usize),
+    #[error("ranges have different hand sizes")]
+    MixedHandSizes,
+    #[error("option not supported for omaha: {0}")]
+    UnsupportedOption(&'static str),
+    #[error(transparent)]
+    Simulator(#[from] SimulatorError),
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::OmahaError;
+use crate::constants::{CARD_COUNT, SUIT_COUNT};
+use crate::hand_range::{char_to_rank, char_to_suit};
+
+/// Mask of every card of the first suit
+const SUIT_CARDS: u64 = 0x1_1111_1111_1111;
+
+/// A single omaha hand with a weight
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OmahaHand {
+    /// 64 bit mask of the hole cards
+    pub mask: u64,
+    /// Weight of the hand from 0 to 1
+    pub weight: f32,
+}
+
+impl OmahaHand {
+    /// Hole cards in ascending order
+    pub fn cards(&self) -> Vec<u8> {
+        (0..CARD_COUNT)
+            .filter(|c| (self.mask >> c) & 1 != 0)
+            .collect()
+    }
+}
+
+/// Element of an omaha hand pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CardPattern {
+    /// A specific card, "As"
+    Card(u8),
+    /// Any card of a rank, "A"
+    Rank(u8),
+    /// Any card, "*"
+    Any,
+}
+
+/// Suit structure a hand must have, written after a `$`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SuitQualifier {
+    /// Two suits with two or more cards, "$ds"
+    DoubleSuited,
+    /// Exactly one suit with two or more cards, "$ss"
+    SingleSuited,
+    /// No two cards of the same suit, "$r"
+    Rainbow,
+}
+
+impl SuitQualifier {
+    fn matches(self, mask: u64) -> bool {
+        let suited = (0..SUIT_COUNT)
+            .filter(|s| (mask & (SUIT_CARDS << s)).count_ones() >= 2)
+            .count();
+        match self {
+            SuitQualifier::DoubleSuited => suited == 2,
+            SuitQualifier::SingleSuited => suited == 1,
+            SuitQualifier::Rainbow => suited == 0,
+        }
+    }
+}
+
+/// A range of 4 or 5 card omaha hands
+///
+/// Ranges are comma separated patterns, each made of one element per hole card:
+/// a card such as `As`, a rank such as `A` or `*` for any card.
+/// `$ds`, `$ss` and `$r` limit a pattern to double suited, single suited
+/// and rainbow hands, and `@50` gives it a weight from 0 to 100.
+///
+/// # Example
+/// ```
+/// use rust_poker::omaha::OmahaRange;
+/// let range = OmahaRange::parse("AA**$ds,KKQQ@50", 4).unwrap();
+/// assert_eq!(range.hand_size(), 4);
+/// assert!(!range.is_empty());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OmahaRange {
+    hand_size: usize,
+    hands: Vec<OmahaHand>,
+}
+
+impl OmahaRange {
+    /// Parse a range of hands with `hand_size` hole cards
+    ///
+    /// Hands matched by more than one pattern take the weight of the last one
+    pub fn parse(text: &str, hand_size: usize) -> Result<Self, OmahaError> {
+        if hand_size != 4 && hand_size != 5 {
+            return Err(OmahaError::InvalidHandSize(hand_size));
+        }
+        let mut weights: HashMap<u64, f32> = HashMap::new();
+        for entry in text.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (masks, weight) = parse_entry(entry, hand_size)?;
+            for mask in masks {
+                weights.insert(mask, weight);
+            }
+        }
+        let mut hands: Vec<OmahaHand> = weights
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .map(|(mask, weight)| OmahaHand { mask, weight })
+            .collect();
+        hands.sort_by_key(|h| h.mask);
+        Ok(OmahaRange { hand_size, hands })
+    }
+
+    /// Number of hole cards in each hand
+    pub fn hand_size(&self) -> usize {
+        self.hand_size
+    }
+
+    /// Hands in the range, sorted by card mask
+    pub fn hands(&self) -> &[OmahaHand] {
+        &self.hands
+    }
+
+    /// Number of hands in the range
+    pub fn len(&self) -> usize {
+        self.hands.len()
+    }
+
+    /// Is the range empty
+    pub fn is_empty(&self) -> bool {
+        self.hands.is_empty()
+    }
+
+    /// Remove hands that use any of the cards in `mask`
+    pub fn remove_conflicting_hands(&mut self, mask: u64) {
+        self.hands.retain(|h| (h.mask & mask) == 0);
+    }
+}
+
+/// Parse one pattern into the masks of the hands it matches and its weight
+fn parse_entry(entry: &str, hand_size: usize) -> Result<(Vec<u64>, f32), OmahaError> {
+    let invalid = || OmahaError::InvalidRange(entry.to_string());
+    let text = entry.to_lowercase();
+    let (text, weight) = match text.find('@') {
+        Some(i) => {
+            let weight: f32 = text[i + 1..].parse().map_err(|_| invalid())?;
+            if !(0.0..=100.0).contains(&weight) {
+                return Err(invalid());
+            }
+            (&text[..i], weight / 100.0)
+        }
+        None => (&text[..], 1.0),
+    };
+    let mut parts = text.split('$');
+    let cards = parts.next().unwrap_or("");
+    let qualifiers = parts
+        .map(|q| match q {
+            "ds" => Ok(SuitQualifier::DoubleSuited),
+            "ss" => Ok(SuitQualifier::SingleSuited),
+            "r" => Ok(SuitQualifier::Rainbow),
+            _ => Err(invalid()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut patterns = Vec::new();
+    if cards == "random" || cards == "any" {
+        patterns.resize(hand_size, CardPattern::Any);
+    } else {
+        let chars: Vec<char> = cards.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '*' {
+                patterns.push(CardPattern::Any);
+                i += 1;
+                continue;
+            }
+            let rank = char_to_rank(chars[i]);
+            if rank == u8::MAX {
+                return Err(invalid());
+            }
+            match chars.get(i + 1).map(|c| char_to_suit(*c)) {
+                Some(suit) if suit != u8::MAX => {
+                    patterns.push(CardPattern::Card(4 * rank + suit));
+                    i += 2;
+                }
+                _ => {
+                    patterns.push(CardPattern::Rank(rank));
+                    i += 1;
+                }
+            }
+        }
+    }
+    if patterns.len() != hand_size {
+        return Err(invalid());
+    }
+    // fixed cards first so wildcards are only filled in once
+    patterns.sort();
+
+    let mut masks = Vec::new();
+    expand_patterns(&patterns, 0, &mut masks);
+    masks.sort_unstable();
+    masks.dedup();
+    masks.retain(|mask| qualifiers.iter().all(|q| q.matches(*mask)));
+    Ok((masks, weight))
+}
+
+/// Push the mask of every hand matching `patterns` given the cards already used
+fn expand_patterns(patterns: &[CardPattern], used_mask: u64, masks: &mut Vec<u64>) {
+    match patterns.first() {
+        None => masks.push(used_mask),
+        Some(CardPattern::Any) => {
+            choose_cards(patterns.len(), 0, used_mask, masks);
+        }
+        Some(pattern) => {
+            let candidates: Vec<u8> = match *pattern {
+                CardPattern::Card(card) => vec![card],
+                CardPattern::Rank(rank) => (0..SUIT_COUNT).map(|s| 4 * rank + s).collect(),
+                CardPattern::Any => unreachable!(),
+            };
+            for card in candidates {
+                if (used_mask & (1u64 << card)) == 0 {
+                    expand_patterns(&patterns[1..], used_mask | (1u64 << card), masks);
+                }
+            }
+        }
+    }
+}
+
+/// Push every way of adding `count` unused cards of index `start` or higher
+fn choose_cards(count: usize, start: u8, used_mask: u64, masks: &mut Vec<u64>) {
+    if count == 0 {
+        masks.push(used_mask);
+        return;
+    }
+    for card in start..CARD_COUNT {
+        if (used_mask & (1u64 << card)) == 0 {
+            choose_cards(count - 1, card + 1, used_mask | (1u64 << card), masks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hand_range::get_card_mask;
+
+    #[test]
+    fn test_parse_patterns() {
+        let range = OmahaRange::parse("AsKsQhJh", 4).unwrap();
+        assert_eq!(range.len(), 1);
+        assert_eq!(range.hands()[0].mask, get_card_mask("AsKsQhJh"));
+
+        // 6 pairs of aces, any two of the other 48 cards
+        let range = OmahaRange::parse("AA**", 4).unwrap();
+        assert_eq!(range.len(), 6 * 1128 + 4 * 48 + 1);
+        assert!(range
+            .hands()
+            .iter()
+            .all(|h| (h.mask & get_card_mask("AsAhAdAc")).count_ones() >= 2));
+
+        assert_eq!(OmahaRange::parse("random", 4).unwrap().len(), 270725);
+        assert_eq!(OmahaRange::parse("KQJT9", 5).unwrap().len(), 1024);
+    }
+
+    #[test]
+    fn test_parse_suit_qualifiers() {
+        let ds = OmahaRange::parse("AAKK$ds", 4).unwrap();
+        // AxKx AyKy, with x and y the two suits
+        assert_eq!(ds.len(), 6);
+        let ss = OmahaRange::parse("AAKK$ss", 4).unwrap();
+        assert_eq!(ss.len(), 24);
+        let r = OmahaRange::parse("AAKK$r", 4).unwrap();
+        assert_eq!(r.len(), 6);
+        assert_eq!(OmahaRange::parse("AAKK", 4).unwrap().len(), 36);
+    }
+
+    #[test]
+    fn test_parse_weights() {
+        let range = OmahaRange::parse("AAKK@50,AsAhKsKh@25,QQJJ@0", 4).unwrap();
+        assert_eq!(range.len(), 36);
+        for hand in range.hands() {
+            if hand.mask == get_card_mask("AsAhKsKh") {
+                assert_eq!(hand.weight, 0.25);
+            } else {
+                assert_eq!(hand.weight, 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(OmahaRange::parse("AA*", 4).is_err());
+        assert!(OmahaRange::parse("AA***", 4).is_err());
+        assert!(OmahaRange::parse("AAXX", 4).is_err());
+        assert!(OmahaRange::parse("AAKK$xx", 4).is_err());
+        assert!(OmahaRange::parse("AAKK@150", 4).is_err());
+        assert!(OmahaRange::parse("AAKK", 6).is_err());
+    }
+}
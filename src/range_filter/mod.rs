@@ -178,7 +178,7 @@ mod tests {
 
     #[test]
     fn test_get_made_hand_category() {
-        let hole_cards = Combo(0u8, 1u8, 100);
+        let hole_cards = Combo(0u8, 1u8, 100.0);
         let board = 0b11100;
         assert!(get_made_hand_category(&hole_cards, board) == MadeHandCategories::QuadsOrBetter);
     }
@@ -186,14 +186,14 @@ mod tests {
     #[test]
     fn test_get_draw_hand_category() {
         {
-            let hole_cards = Combo(0u8, 4, 100);
+            let hole_cards = Combo(0u8, 4, 100.0);
             let board = 0b0001000100000010;
             assert!(
                 get_draw_hand_category(&hole_cards, board) == DrawHandCategories::TwoCardFlushDraw
             );
         }
         {
-            let hole_cards = Combo(0u8, 1u8, 100);
+            let hole_cards = Combo(0u8, 1u8, 100.0);
             let board = get_card_mask("4s5sAs");
             assert_eq!(
                 get_draw_hand_category(&hole_cards, board),
@@ -201,7 +201,7 @@ mod tests {
             );
         }
         {
-            let hole_cards = Combo(4u8, 5u8, 100); // 3, 3
+            let hole_cards = Combo(4u8, 5u8, 100.0); // 3, 3
             let board = get_card_mask("4s5h6c");
             assert_eq!(
                 get_draw_hand_category(&hole_cards, board),
@@ -209,7 +209,7 @@ mod tests {
             );
         }
         {
-            let hole_cards = Combo(8u8 * 4, 0, 100); // T, 2
+            let hole_cards = Combo(8u8 * 4, 0, 100.0); // T, 2
             let board = get_card_mask("JcQsKd");
             assert_eq!(
                 get_draw_hand_category(&hole_cards, board),
@@ -0,0 +1,465 @@
+/*
+ * Reads and writes weighted ranges in the formats used by
+ * other poker tools
+ *
+ * Equilab:  "AA,AKs@50,AhKd@25" (weights 0-100)
+ * PioSolver: "AA:1.0,AKs:0.5,AhKd:0.25" (frequencies 0-1)
+ * GTO+:      "AA,[50]AKs[/50],[25]AhKd[/25]" (weights 0-100)
+ * Monker:    one "hand:frequency" entry per line
+ */
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::hand_range::{Combo, HandRange, HandRangeError};
+
+#[derive(Debug, Error)]
+pub enum RangeFormatError {
+    #[error("unknown range format: {0}")]
+    UnknownFormat(String),
+    #[error("invalid hand: {0}")]
+    InvalidHand(String),
+    #[error("invalid weight: {0}")]
+    InvalidWeight(String),
+    #[error("unbalanced weight group: {0}")]
+    UnbalancedGroup(String),
+}
+
+/// A text format for weighted hand ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeFormat {
+    /// Equilab-like range strings, "AKs@50"
+    Equilab,
+    /// PioSolver range strings, "AKs:0.5"
+    PioSolver,
+    /// GTO+ range strings, "[50]AKs[/50]"
+    GtoPlus,
+    /// MonkerSolver range files, one "AKs:0.5" per line
+    Monker,
+}
+
+impl FromStr for RangeFormat {
+    type Err = RangeFormatError;
+
+    /// Get a format from its name
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::range_format::RangeFormat;
+    /// let format: RangeFormat = "pio".parse().unwrap();
+    /// assert_eq!(format, RangeFormat::PioSolver);
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "equilab" => Ok(RangeFormat::Equilab),
+            "pio" | "piosolver" => Ok(RangeFormat::PioSolver),
+            "gto+" | "gtoplus" => Ok(RangeFormat::GtoPlus),
+            "monker" | "monkersolver" => Ok(RangeFormat::Monker),
+            _ => Err(RangeFormatError::UnknownFormat(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for RangeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RangeFormat::Equilab => "equilab",
+            RangeFormat::PioSolver => "piosolver",
+            RangeFormat::GtoPlus => "gto+",
+            RangeFormat::Monker => "monker",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl RangeFormat {
+    /// Parse a range string in this format
+    ///
+    /// Later entries override the weight of earlier entries for the same combo,
+    /// so "AKs:0.5,AsKs:1" keeps AsKs at full weight.  Combos with zero weight are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::range_format::RangeFormat;
+    /// let range = RangeFormat::PioSolver.parse("AA:1.0,AKs:0.5,AhKd:0.25").unwrap();
+    /// assert_eq!(range.hands.len(), 11);
+    /// ```
+    pub fn parse(self, text: &str) -> Result<HandRange, RangeFormatError> {
+        let entries = match self {
+            RangeFormat::Equilab => {
+                return text.parse().map_err(|e| match e {
+                    HandRangeError::InvalidHand(hand) => RangeFormatError::InvalidHand(hand),
+                })
+            }
+            RangeFormat::PioSolver | RangeFormat::Monker => parse_frequency_entries(text)?,
+            RangeFormat::GtoPlus => parse_gto_plus_entries(text)?,
+        };
+        let mut weights: HashMap<(u8, u8), f32> = HashMap::new();
+        let mut order: Vec<(u8, u8)> = Vec::new();
+        for (hand, weight) in entries {
+            let range = HandRange::from_hand_string(&hand)
+                .ok_or_else(|| RangeFormatError::InvalidHand(hand.clone()))?;
+            for c in range.hands {
+                if weights.insert((c.0, c.1), weight).is_none() {
+                    order.push((c.0, c.1));
+                }
+            }
+        }
+        let combos = order
+            .iter()
+            .map(|cards| Combo(cards.0, cards.1, weights[cards]))
+            .filter(|c| c.2 > 0.0)
+            .collect();
+        Ok(HandRange::from_combos(combos))
+    }
+
+    /// Write a range to a string in this format
+    ///
+    /// Hand classes whose combos all share a weight are written as one entry ("AKs"),
+    /// everything else is written combo by combo
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::HandRange;
+    /// use rust_poker::range_format::RangeFormat;
+    /// let range = HandRange::from_string("AA,AKs@50".to_string());
+    /// assert_eq!(RangeFormat::PioSolver.write(&range), "AA,AKs:0.5");
+    /// ```
+    pub fn write(self, range: &HandRange) -> String {
+        let entries = group_hand_classes(range);
+        match self {
+            RangeFormat::Equilab => entries
+                .iter()
+                .map(|(hand, weight)| {
+                    if *weight == 100.0 {
+                        hand.clone()
+                    } else {
+                        format!("{}@{}", hand, weight)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(","),
+            RangeFormat::PioSolver => entries
+                .iter()
+                .map(|(hand, weight)| {
+                    if *weight == 100.0 {
+                        hand.clone()
+                    } else {
+                        format!("{}:{}", hand, weight / 100.0)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(","),
+            RangeFormat::Monker => entries
+                .iter()
+                .map(|(hand, weight)| format!("{}:{}", hand, weight / 100.0))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            RangeFormat::GtoPlus => {
+                // group consecutive entries with the same weight
+                let mut groups: Vec<(f32, Vec<String>)> = Vec::new();
+                for (hand, weight) in entries {
+                    match groups.last_mut() {
+                        Some((w, hands)) if *w == weight => hands.push(hand),
+                        _ => groups.push((weight, vec![hand])),
+                    }
+                }
+                groups
+                    .iter()
+                    .map(|(weight, hands)| {
+                        if *weight == 100.0 {
+                            hands.join(",")
+                        } else {
+                            format!("[{}]{}[/{}]", weight, hands.join(","), weight)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(",")
+            }
+        }
+    }
+}
+
+/// Parse a range string using the format name
+///
+/// # Example
+///
+/// ```
+/// use rust_poker::range_format::parse_range;
+/// let range = parse_range("[50]AKs,AQs[/50],KK", "gto+").unwrap();
+/// assert_eq!(range.hands.len(), 14);
+/// ```
+pub fn parse_range(text: &str, format_name: &str) -> Result<HandRange, RangeFormatError> {
+    format_name.parse::<RangeFormat>()?.parse(text)
+}
+
+/// Write a range to a string using the format name
+///
+/// # Example
+///
+/// ```
+/// use rust_poker::hand_range::HandRange;
+/// use rust_poker::range_format::write_range;
+/// let range = HandRange::from_string("KK,AKs@50".to_string());
+/// assert_eq!(write_range(&range, "gto+").unwrap(), "[50]AKs[/50],KK");
+/// ```
+pub fn write_range(range: &HandRange, format_name: &str) -> Result<String, RangeFormatError> {
+    Ok(format_name.parse::<RangeFormat>()?.write(range))
+}
+
+/// Hand ranges are serialized as equilab range strings
+impl Serialize for HandRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&RangeFormat::Equilab.write(self))
+    }
+}
+
+/// Serde representation of a hand range
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HandRangeRepr {
+    Notation(String),
+    Combos(Vec<Combo>),
+}
+
+impl<'de> Deserialize<'de> for HandRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match HandRangeRepr::deserialize(deserializer)? {
+            HandRangeRepr::Notation(text) => text.parse().map_err(de::Error::custom)?,
+            HandRangeRepr::Combos(combos) => HandRange::from_combos(combos),
+        })
+    }
+}
+
+/// Split "hand:frequency" entries separated by commas or new lines
+fn parse_frequency_entries(text: &str) -> Result<Vec<(String, f32)>, RangeFormatError> {
+    let mut entries = Vec::new();
+    for entry in text.split([',', '\n']) {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let mut parts = entry.splitn(2, ':');
+        let hand = parts.next().unwrap_or("").trim().to_string();
+        let weight = match parts.next() {
+            Some(freq) => parse_weight(freq, 100.0)?,
+            None => 100.0,
+        };
+        entries.push((hand, weight));
+    }
+    Ok(entries)
+}
+
+/// Split GTO+ entries, where weighted hands are wrapped in "[weight]...[/weight]"
+fn parse_gto_plus_entries(text: &str) -> Result<Vec<(String, f32)>, RangeFormatError> {
+    let mut entries = Vec::new();
+    let mut weight = 100.0;
+    let mut in_group = false;
+    for entry in text.split([',', '\n']) {
+        let mut entry = entry.trim();
+        if entry.starts_with('[') {
+            if in_group {
+                return Err(RangeFormatError::UnbalancedGroup(entry.to_string()));
+            }
+            let end = entry
+                .find(']')
+                .ok_or_else(|| RangeFormatError::UnbalancedGroup(entry.to_string()))?;
+            weight = parse_weight(&entry[1..end], 1.0)?;
+            in_group = true;
+            entry = &entry[end + 1..];
+        }
+        let mut closes_group = false;
+        if let Some(start) = entry.find("[/") {
+            if !in_group {
+                return Err(RangeFormatError::UnbalancedGroup(entry.to_string()));
+            }
+            closes_group = true;
+            entry = &entry[..start];
+        }
+        let hand = entry.trim();
+        if !hand.is_empty() {
+            entries.push((hand.to_string(), weight));
+        }
+        if closes_group {
+            weight = 100.0;
+            in_group = false;
+        }
+    }
+    if in_group {
+        return Err(RangeFormatError::UnbalancedGroup(text.to_string()));
+    }
+    Ok(entries)
+}
+
+/// Parse a weight and scale it to 0-100
+fn parse_weight(text: &str, scale: f32) -> Result<f32, RangeFormatError> {
+    match text.trim().parse::<f32>() {
+        Ok(w) if w >= 0.0 && w * scale <= 100.0 => Ok(w * scale),
+        _ => Err(RangeFormatError::InvalidWeight(text.to_string())),
+    }
+}
+
+/// Get the number of combos in a hand class
+fn class_size(rank1: u8, rank2: u8, suited: bool) -> usize {
+    if rank1 == rank2 {
+        6
+    } else if suited {
+        4
+    } else {
+        12
+    }
+}
+
+/// Collapse a range into (hand, weight) entries, strongest hand classes first
+fn group_hand_classes(range: &HandRange) -> Vec<(String, f32)> {
+    let mut hands = range.hands.clone();
+    hands.sort();
+    hands.dedup();
+    hands.reverse();
+
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < hands.len() {
+        let rank1 = hands[i].0 >> 2;
+        let rank2 = hands[i].1 >> 2;
+        // suited and offsuit combos of the same ranks are interleaved
+        let mut j = i;
+        while j < hands.len() && hands[j].0 >> 2 == rank1 && hands[j].1 >> 2 == rank2 {
+            j += 1;
+        }
+        let same_ranks = &hands[i..j];
+        for &s in &[true, false] {
+            if rank1 == rank2 && s {
+                continue;
+            }
+            let class: Vec<&Combo> = same_ranks
+                .iter()
+                .filter(|c| rank1 == rank2 || ((c.0 & 3) == (c.1 & 3)) == s)
+                .collect();
+            if class.is_empty() {
+                continue;
+            }
+            let weight = class[0].2;
+            if class.len() == class_size(rank1, rank2, s) && class.iter().all(|c| c.2 == weight) {
+                entries.push((class[0].hand_class(), weight));
+            } else {
+                entries.extend(class.iter().map(|c| (c.to_string(), c.2)));
+            }
+        }
+        i = j;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!(
+            "Pio".parse::<RangeFormat>().unwrap(),
+            RangeFormat::PioSolver
+        );
+        assert_eq!("gto+".parse::<RangeFormat>().unwrap(), RangeFormat::GtoPlus);
+        assert_eq!(
+            "monker".parse::<RangeFormat>().unwrap(),
+            RangeFormat::Monker
+        );
+        assert!("flopzilla".parse::<RangeFormat>().is_err());
+    }
+
+    #[test]
+    fn test_parse_pio() {
+        let range = parse_range("AA:1.0,AKs:0.5,AhKd:0.25", "pio").unwrap();
+        assert_eq!(range.hands.len(), 11);
+        for c in &range.hands {
+            match (c.0 >> 2, c.1 >> 2) {
+                (12, 12) => assert_eq!(c.2, 100.0),
+                _ if (c.0 & 3) == (c.1 & 3) => assert_eq!(c.2, 50.0),
+                _ => assert_eq!(c.2, 25.0),
+            }
+        }
+        // per combo overrides and zero weights
+        let range = parse_range("AKs:0.5,AsKs:1,AhKh:0", "pio").unwrap();
+        assert_eq!(range.hands.len(), 3);
+        assert!(range
+            .hands
+            .iter()
+            .any(|c| c.to_string() == "AsKs" && c.2 == 100.0));
+        // errors
+        assert!(parse_range("AKx:0.5", "pio").is_err());
+        assert!(parse_range("AKs:1.5", "pio").is_err());
+    }
+
+    #[test]
+    fn test_parse_monker_lines() {
+        let range = parse_range("AA:1.0\nAhKd:0.25\n", "monker").unwrap();
+        assert_eq!(range.hands.len(), 7);
+    }
+
+    #[test]
+    fn test_parse_gto_plus() {
+        let range = parse_range("[37.25]AKs, AQs[/37.25], KK", "gto+").unwrap();
+        assert_eq!(range.hands.len(), 14);
+        assert_eq!(range.hands.iter().filter(|c| c.2 == 37.25).count(), 8);
+        assert!(parse_range("[50]AKs", "gto+").is_err());
+    }
+
+    #[test]
+    fn test_parse_equilab() {
+        let range = parse_range("QQ+, AKs@50", "equilab").unwrap();
+        assert_eq!(range.hands.len(), 22);
+        assert!(matches!(
+            parse_range("QQ,AKx", "equilab"),
+            Err(RangeFormatError::InvalidHand(hand)) if hand == "AKx"
+        ));
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let range = HandRange::from_string("AA,KK@50,AKs@37.25,AhKd@25,QJo".to_string());
+        for format in &[
+            RangeFormat::Equilab,
+            RangeFormat::PioSolver,
+            RangeFormat::GtoPlus,
+            RangeFormat::Monker,
+        ] {
+            let text = format.write(&range);
+            let parsed = format.parse(&text).unwrap();
+            assert_eq!(parsed.hands.len(), range.hands.len());
+            for (a, b) in parsed.hands.iter().zip(range.hands.iter()) {
+                assert_eq!(a, b);
+                assert!((a.2 - b.2).abs() < 1e-4);
+            }
+        }
+        assert_eq!(
+            RangeFormat::PioSolver.write(&range),
+            "AA,AKs:0.3725,AhKd:0.25,KK:0.5,QJo"
+        );
+    }
+
+    #[test]
+    fn test_hand_range_serde() {
+        let range = HandRange::from_string("QQ+,AKs@37.25,AhKd@50".to_string());
+        let json = serde_json::to_string(&range).unwrap();
+        assert_eq!(json, r#""AA,AKs@37.25,AhKd@50,KK,QQ""#);
+        let parsed: HandRange = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.hands, range.hands);
+        // list of combos
+        let parsed: HandRange =
+            serde_json::from_str(r#"[{"hand":"AhKd","weight":50},{"hand":"AsAh"}]"#).unwrap();
+        assert_eq!(parsed.hands.len(), 2);
+        assert_eq!(parsed.hands[0].2, 50.0);
+        assert_eq!(parsed.hands[1].2, 100.0);
+        // invalid notation
+        assert!(serde_json::from_str::<HandRange>(r#""AKx,QQ""#).is_err());
+        assert!(serde_json::from_str::<HandRange>(r#""garbage""#).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+/*
+ * Named hand ranges stored in a tree of folders
+ *
+ * Ranges are addressed by paths such as "BTN/RFI" or "BB/vs BTN/call"
+ * and can be saved to and loaded from JSON or TOML files, where each
+ * folder is a table and each range is an equilab-like range string.
+ * Range strings may include other ranges with "@path" references
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+use serde_json::{Map, Value};
+
+use crate::hand_range::{HandRange, HandRangeError};
+use crate::range_format::RangeFormat;
+
+#[derive(Debug, Error)]
+pub enum RangeLibraryError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("toml parse error: {0}")]
+    TomlParse(#[from] toml::de::Error),
+    #[error("toml write error: {0}")]
+    TomlWrite(#[from] toml::ser::Error),
+    #[error("unsupported file extension: {0}")]
+    UnsupportedExtension(String),
+    #[error("invalid library entry: {0}")]
+    InvalidEntry(String),
+    #[error("range and folder share a path: {0}")]
+    PathConflict(String),
+    #[error("unknown range reference: @{0}")]
+    UnknownReference(String),
+    #[error("range references itself: @{0}")]
+    ReferenceCycle(String),
+    #[error("invalid range: {0}")]
+    InvalidRange(#[from] HandRangeError),
+}
+
+/// A library of named hand ranges
+#[derive(Debug, Clone, Default)]
+pub struct RangeLibrary {
+    ranges: BTreeMap<String, HandRange>,
+}
+
+impl RangeLibrary {
+    /// Creates an empty library
+    pub fn new() -> Self {
+        RangeLibrary {
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Add a range to the library, replacing any range with the same path
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::HandRange;
+    /// use rust_poker::range_library::RangeLibrary;
+    /// let mut library = RangeLibrary::new();
+    /// library.insert("BB/vs BTN/call", HandRange::from_string("22+,A2s+".to_string()));
+    /// assert!(library.get("BB/vs BTN/call").is_some());
+    /// ```
+    pub fn insert(&mut self, path: &str, range: HandRange) {
+        self.ranges.insert(normalize_path(path), range);
+    }
+
+    /// Parse a range string with `parse` and add it to the library
+    pub fn insert_str(&mut self, path: &str, text: &str) -> Result<(), RangeLibraryError> {
+        let range = self.parse(text)?;
+        self.insert(path, range);
+        Ok(())
+    }
+
+    /// Parse a range string, resolving "@path" references against this library
+    ///
+    /// A reference runs until the next comma and is looked up ignoring case.
+    /// Unknown references and invalid hands are an error
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::HandRange;
+    /// use rust_poker::range_library::RangeLibrary;
+    /// let mut library = RangeLibrary::new();
+    /// library.insert("BTN/RFI", HandRange::from_string("22+".to_string()));
+    /// let range = library.parse("@BTN/RFI,AKs").unwrap();
+    /// assert_eq!(range.hands.len(), 82);
+    /// assert!(library.parse("@CO/RFI,AKs").is_err());
+    /// ```
+    pub fn parse(&self, text: &str) -> Result<HandRange, RangeLibraryError> {
+        let mut combos = Vec::new();
+        for item in text.split(',') {
+            match item.trim().strip_prefix('@') {
+                Some(path) => {
+                    let range = self
+                        .find(path)
+                        .ok_or_else(|| RangeLibraryError::UnknownReference(path.to_string()))?;
+                    combos.extend_from_slice(&range.hands);
+                }
+                None => combos.extend(item.parse::<HandRange>()?.hands),
+            }
+        }
+        Ok(HandRange::from_combos(combos))
+    }
+
+    /// Get a range by path
+    pub fn get(&self, path: &str) -> Option<&HandRange> {
+        self.ranges.get(&normalize_path(path))
+    }
+
+    /// Get a range by path, ignoring case
+    pub fn find(&self, path: &str) -> Option<&HandRange> {
+        let path = normalize_path(path);
+        self.ranges.get(&path).or_else(|| {
+            self.ranges
+                .iter()
+                .find(|(p, _)| p.to_lowercase() == path.to_lowercase())
+                .map(|(_, r)| r)
+        })
+    }
+
+    /// Remove a range by path
+    pub fn remove(&mut self, path: &str) -> Option<HandRange> {
+        self.ranges.remove(&normalize_path(path))
+    }
+
+    /// Iterate over all range paths in sorted order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.ranges.keys().map(|p| p.as_str())
+    }
+
+    /// Get the paths of all ranges inside a folder and its sub folders
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_poker::hand_range::HandRange;
+    /// use rust_poker::range_library::RangeLibrary;
+    /// let mut library = RangeLibrary::new();
+    /// library.insert("BTN/RFI", HandRange::from_string("22+".to_string()));
+    /// library.insert("BB/vs BTN/call", HandRange::from_string("33+".to_string()));
+    /// assert_eq!(library.folder("BB"), vec!["BB/vs BTN/call"]);
+    /// ```
+    pub fn folder(&self, folder: &str) -> Vec<&str> {
+        let prefix = format!("{}/", normalize_path(folder));
+        self.paths().filter(|p| p.starts_with(&prefix)).collect()
+    }
+
+    /// Number of ranges in the library
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Is the library empty
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Write the library as a JSON string
+    pub fn to_json(&self) -> Result<String, RangeLibraryError> {
+        Ok(serde_json::to_string_pretty(&self.to_tree()?)?)
+    }
+
+    /// Read a library from a JSON string
+    pub fn from_json(text: &str) -> Result<Self, RangeLibraryError> {
+        let mut library = RangeLibrary::new();
+        library.add_tree(&serde_json::from_str(text)?)?;
+        Ok(library)
+    }
+
+    /// Write the library as a TOML string
+    pub fn to_toml(&self) -> Result<String, RangeLibraryError> {
+        Ok(toml::to_string_pretty(&self.to_tree()?)?)
+    }
+
+    /// Read a library from a TOML string
+    pub fn from_toml(text: &str) -> Result<Self, RangeLibraryError> {
+        let mut library = RangeLibrary::new();
+        library.add_tree(&toml::from_str(text)?)?;
+        Ok(library)
+    }
+
+    /// Save the library to a ".json" or ".toml" file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RangeLibraryError> {
+        let text = match extension(path.as_ref()).as_str() {
+            "json" => self.to_json()?,
+            "toml" => self.to_toml()?,
+            ext => return Err(RangeLibraryError::UnsupportedExtension(ext.to_string())),
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Load a library from a ".json" or ".toml" file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RangeLibraryError> {
+        let mut library = RangeLibrary::new();
+        library.merge(path)?;
+        Ok(library)
+    }
+
+    /// Add the ranges of a ".json" or ".toml" file to the library
+    ///
+    /// References in the file can point to ranges already in the library.
+    /// The library is left unchanged if the file can't be read
+    pub fn merge<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RangeLibraryError> {
+        let text = fs::read_to_string(&path)?;
+        let tree = match extension(path.as_ref()).as_str() {
+            "json" => serde_json::from_str(&text)?,
+            "toml" => toml::from_str(&text)?,
+            ext => return Err(RangeLibraryError::UnsupportedExtension(ext.to_string())),
+        };
+        let mut library = self.clone();
+        library.add_tree(&tree)?;
+        *self = library;
+        Ok(())
+    }
+
+    /// Build a tree of folders, with range strings as leaves
+    fn to_tree(&self) -> Result<Value, RangeLibraryError> {
+        let mut root = Map::new();
+        for (path, range) in &self.ranges {
+            let segments: Vec<&str> = path.split('/').collect();
+            let (name, folders) = segments.split_last().unwrap();
+            let mut node = &mut root;
+            for folder in folders {
+                let entry = node
+                    .entry(folder.to_string())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                node = match entry {
+                    Value::Object(map) => map,
+                    _ => return Err(RangeLibraryError::PathConflict(path.clone())),
+                };
+            }
+            if node.contains_key(*name) {
+                return Err(RangeLibraryError::PathConflict(path.clone()));
+            }
+            node.insert(
+                name.to_string(),
+                Value::String(RangeFormat::Equilab.write(range)),
+            );
+        }
+        Ok(Value::Object(root))
+    }
+
+    /// Add the ranges of a tree of folders, resolving references
+    /// in whatever order the ranges depend on each other
+    fn add_tree(&mut self, tree: &Value) -> Result<(), RangeLibraryError> {
+        let mut pending = Vec::new();
+        let mut stack = vec![(String::new(), tree)];
+        while let Some((prefix, node)) = stack.pop() {
+            let map = match node {
+                Value::Object(map) => map,
+                _ => return Err(RangeLibraryError::InvalidEntry(prefix)),
+            };
+            for (name, value) in map {
+                let path = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", prefix, name)
+                };
+                match value {
+                    Value::String(text) => pending.push((path, text.as_str())),
+                    Value::Object(_) => stack.push((path, value)),
+                    _ => return Err(RangeLibraryError::InvalidEntry(path)),
+                }
+            }
+        }
+
+        // each pass adds the ranges whose references are all known
+        while !pending.is_empty() {
+            let mut unresolved = Vec::new();
+            let mut missing = String::new();
+            for (path, text) in pending.iter() {
+                match self.parse(text) {
+                    Ok(range) => self.insert(path, range),
+                    Err(RangeLibraryError::UnknownReference(reference)) => {
+                        missing = reference;
+                        unresolved.push((path.clone(), *text));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if unresolved.len() == pending.len() {
+                // a missing range that is still pending depends on itself
+                let missing_path = normalize_path(&missing).to_lowercase();
+                let in_cycle = unresolved
+                    .iter()
+                    .any(|(p, _)| normalize_path(p).to_lowercase() == missing_path);
+                return Err(if in_cycle {
+                    RangeLibraryError::ReferenceCycle(missing)
+                } else {
+                    RangeLibraryError::UnknownReference(missing)
+                });
+            }
+            pending = unresolved;
+        }
+        Ok(())
+    }
+}
+
+/// Remove empty segments and surrounding whitespace from a path
+fn normalize_path(path: &str) -> String {
+    path.split('/')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Temporary file path unique to a test and process
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "rust_poker_range_library_{}_{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn test_library() -> RangeLibrary {
+        let mut library = RangeLibrary::new();
+        library
+            .insert_str("BTN/RFI", "22+,A2s+,KTs+,AJo+@50")
+            .unwrap();
+        library
+            .insert_str("BB/vs BTN/call", "22+,A2s+@37.25")
+            .unwrap();
+        library.insert_str("BB/vs BTN/3bet", "TT+,AKs,KQs").unwrap();
+        library
+    }
+
+    #[test]
+    fn test_paths() {
+        let library = test_library();
+        assert_eq!(library.len(), 3);
+        assert!(library.get("/BTN/RFI/").is_some());
+        assert!(library.get("btn/rfi").is_none());
+        assert!(library.find("btn/rfi").is_some());
+        assert_eq!(library.folder("BB/vs BTN").len(), 2);
+        assert_eq!(library.folder("BTN"), vec!["BTN/RFI"]);
+    }
+
+    #[test]
+    fn test_references() {
+        let mut library = test_library();
+        library
+            .insert_str("BB/vs BTN/continue", "@BB/vs BTN/call,@BB/vs BTN/3bet")
+            .unwrap();
+        let call = library.get("BB/vs BTN/call").unwrap().hands.len();
+        let three_bet = library.get("BB/vs BTN/3bet").unwrap().hands.len();
+        let continue_range = library.get("BB/vs BTN/continue").unwrap();
+        // only KQs is not part of the calling range
+        assert_eq!(three_bet, 38);
+        assert_eq!(continue_range.hands.len(), call + 4);
+        // unknown references are an error
+        assert!(matches!(
+            library.parse("@CO/RFI,AA"),
+            Err(RangeLibraryError::UnknownReference(path)) if path == "CO/RFI"
+        ));
+        assert!(library.insert_str("CO/RFI", "AA,@CO/limp").is_err());
+        assert!(library.get("CO/RFI").is_none());
+        assert!(matches!(
+            library.parse("@BTN/RFI,AKx"),
+            Err(RangeLibraryError::InvalidRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_references() {
+        // BB sorts before BTN, so the reference is read before its target
+        let library = RangeLibrary::from_json(
+            r#"{"BB": {"vs BTN": {"continue": "@btn/rfi,AA"}}, "BTN": {"RFI": "KK,AKs"}}"#,
+        )
+        .unwrap();
+        assert_eq!(library.get("BB/vs BTN/continue").unwrap().hands.len(), 16);
+        assert!(matches!(
+            RangeLibrary::from_json(r#"{"A": "@B,AA", "B": "@A,KK"}"#),
+            Err(RangeLibraryError::ReferenceCycle(_))
+        ));
+        assert!(matches!(
+            RangeLibrary::from_json(r#"{"A": "@C,AA", "B": "KK"}"#),
+            Err(RangeLibraryError::UnknownReference(_))
+        ));
+    }
+
+    #[test]
+    fn test_merge_cross_file_references() {
+        let base_path = temp_path("merge_base.toml");
+        let pack_path = temp_path("merge_pack.json");
+        fs::write(&base_path, "[BTN]\nRFI = \"22+,AKs\"\n").unwrap();
+        fs::write(
+            &pack_path,
+            r#"{"BB": {"vs BTN": {"3bet": "@BTN/RFI,AKo"}}}"#,
+        )
+        .unwrap();
+
+        // the pack alone can't resolve its reference
+        assert!(RangeLibrary::load(&pack_path).is_err());
+        let mut library = RangeLibrary::load(&base_path).unwrap();
+        library.merge(&pack_path).unwrap();
+        fs::remove_file(&base_path).unwrap();
+        fs::remove_file(&pack_path).unwrap();
+        // 78 pairs, 4 AKs and 12 AKo
+        assert_eq!(library.get("BB/vs BTN/3bet").unwrap().hands.len(), 94);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let library = test_library();
+        let json = library.to_json().unwrap();
+        let loaded = RangeLibrary::from_json(&json).unwrap();
+        assert_eq!(loaded.len(), library.len());
+        for path in library.paths() {
+            let a = library.get(path).unwrap();
+            let b = loaded.get(path).unwrap();
+            assert_eq!(a.hands, b.hands);
+            for (c1, c2) in a.hands.iter().zip(b.hands.iter()) {
+                assert_eq!(c1.2, c2.2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let library = test_library();
+        let text = library.to_toml().unwrap();
+        let loaded = RangeLibrary::from_toml(&text).unwrap();
+        assert_eq!(loaded.len(), library.len());
+        assert_eq!(
+            loaded.get("BB/vs BTN/call").unwrap().hands,
+            library.get("BB/vs BTN/call").unwrap().hands
+        );
+    }
+
+    #[test]
+    fn test_path_conflict() {
+        let mut library = test_library();
+        library.insert_str("BTN", "AA").unwrap();
+        assert!(library.to_json().is_err());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let library = test_library();
+        let path = temp_path("save_load.toml");
+        library.save(&path).unwrap();
+        let loaded = RangeLibrary::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), library.len());
+        assert!(library.save("ranges.txt").is_err());
+    }
+}